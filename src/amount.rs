@@ -0,0 +1,339 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
+
+// Key serde_json uses to hand out the literal text of a number when `arbitrary_precision` is enabled
+const SERDE_JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+// Name serde_json recognises to hand out the raw JSON text of a value (`raw_value` feature)
+const SERDE_JSON_RAW_TOKEN: &str = "$serde_json::private::RawValue";
+
+/// A lossless decimal amount for prices, quantities and balances.
+///
+/// Serializes as a JSON string and deserializes from either a string or a JSON number
+/// without ever passing through `f64`. Numbers are read from their JSON text, so this holds
+/// whether or not the `arbitrary-precision` feature is enabled, except for values that were
+/// already parsed into a `serde_json::Value` without it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(Decimal);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmountError(pub String);
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Amount error: {}", self.0)
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+impl Amount {
+    pub const ZERO: Amount = Amount(Decimal::ZERO);
+
+    pub fn new(value: Decimal) -> Self {
+        Self(value)
+    }
+
+    pub fn as_decimal(&self) -> &Decimal {
+        &self.0
+    }
+
+    pub fn into_decimal(self) -> Decimal {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0.is_sign_negative() && !self.0.is_zero()
+    }
+
+    /// Number of digits after the decimal point, as received
+    pub fn scale(&self) -> u32 {
+        self.0.scale()
+    }
+
+    /// Format the amount with the display precision of `currency`, e.g. `"1234.50 USD"`.
+    ///
+    /// Currencies without a known precision are printed exactly as stored.
+    pub fn format_currency(&self, currency: &str) -> String {
+        let currency = currency.to_ascii_uppercase();
+        match currency_scale(&currency) {
+            Some(scale) => {
+                let mut rounded = self
+                    .0
+                    .round_dp_with_strategy(scale, RoundingStrategy::MidpointNearestEven);
+                rounded.rescale(scale);
+                format!("{} {}", rounded, currency)
+            }
+            None => format!("{} {}", self.0.normalize(), currency),
+        }
+    }
+}
+
+/// Display precision for well-known currency codes
+pub fn currency_scale(currency: &str) -> Option<u32> {
+    match currency.to_ascii_uppercase().as_str() {
+        "JPY" | "KRW" => Some(0),
+        "USD" | "EUR" | "GBP" | "CAD" | "AUD" | "CHF" | "SGD" | "HKD" => Some(2),
+        "USDC" | "USDT" | "PYUSD" => Some(6),
+        "BTC" | "LTC" | "BCH" => Some(8),
+        "SOL" => Some(9),
+        "ETH" => Some(18),
+        _ => None,
+    }
+}
+
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        Decimal::from_str_exact(trimmed)
+            .or_else(|_| Decimal::from_scientific(trimmed))
+            .map(Amount)
+            .map_err(|e| AmountError(format!("invalid decimal amount '{}': {}", s, e)))
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<Decimal> for Amount {
+    fn from(value: Decimal) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Amount> for Decimal {
+    fn from(value: Amount) -> Self {
+        value.0
+    }
+}
+
+impl From<i64> for Amount {
+    fn from(value: i64) -> Self {
+        Self(Decimal::from(value))
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(value: u64) -> Self {
+        Self(Decimal::from(value))
+    }
+}
+
+impl Add for Amount {
+    type Output = Amount;
+
+    fn add(self, rhs: Amount) -> Amount {
+        Amount(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Amount {
+    type Output = Amount;
+
+    fn sub(self, rhs: Amount) -> Amount {
+        Amount(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Amount {
+    type Output = Amount;
+
+    fn neg(self) -> Amount {
+        Amount(-self.0)
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // serde_json answers with the raw JSON text; other formats forward to `deserialize_any`
+        deserializer.deserialize_newtype_struct(SERDE_JSON_RAW_TOKEN, AmountVisitor)
+    }
+}
+
+/// Read an amount from a JSON string or number
+impl TryFrom<&Value> for Amount {
+    type Error = AmountError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Amount::from_str(s),
+            Value::Number(n) => Amount::from_str(&n.to_string()),
+            other => Err(AmountError(format!(
+                "expected a decimal amount, got {other}"
+            ))),
+        }
+    }
+}
+
+/// Amounts go into JSON bodies as strings, so building a body never produces a float
+impl From<Amount> for Value {
+    fn from(value: Amount) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+struct AmountVisitor;
+
+impl<'de> Visitor<'de> for AmountVisitor {
+    type Value = Amount;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a decimal amount as a string or number")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Amount, E> {
+        Amount::from_str(v).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Amount, E> {
+        Ok(Amount(Decimal::from(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Amount, E> {
+        Ok(Amount(Decimal::from(v)))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Amount, E> {
+        Decimal::try_from_i128_with_scale(v, 0)
+            .map(Amount)
+            .map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Amount, E> {
+        Err(E::custom(format!(
+            "refusing to decode amount {} from a floating point number; \
+            send amounts as strings or decode them from JSON text",
+            v
+        )))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Amount, D::Error> {
+        deserializer.deserialize_any(AmountVisitor)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Amount, A::Error> {
+        // serde_json hands out raw values, and numbers under `arbitrary_precision`, as a
+        // single-entry map holding their literal text
+        match map.next_key::<String>()? {
+            Some(key) if key == SERDE_JSON_RAW_TOKEN => {
+                let raw: String = map.next_value()?;
+                let literal = if raw.trim_start().starts_with('"') {
+                    serde_json::from_str::<String>(&raw).map_err(de::Error::custom)?
+                } else {
+                    raw
+                };
+                Amount::from_str(&literal).map_err(de::Error::custom)
+            }
+            Some(key) if key == SERDE_JSON_NUMBER_TOKEN => {
+                let literal: String = map.next_value()?;
+                Amount::from_str(&literal).map_err(de::Error::custom)
+            }
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Fill {
+        price: Amount,
+        size: Amount,
+    }
+
+    #[test]
+    fn parses_plain_and_scientific_strings() {
+        assert_eq!("12.3400".parse::<Amount>().unwrap().scale(), 4);
+        assert_eq!("1e-3".parse::<Amount>().unwrap().to_string(), "0.001");
+        assert!("abc".parse::<Amount>().is_err());
+    }
+
+    #[test]
+    fn json_numbers_decode_without_float_rounding() {
+        let fill: Fill =
+            serde_json::from_str(r#"{"price": 0.1000000000000000055511, "size": "3.50"}"#).unwrap();
+        assert_eq!(fill.price.to_string(), "0.1000000000000000055511");
+        assert_eq!(fill.size.to_string(), "3.50");
+    }
+
+    #[test]
+    fn string_forms_are_unescaped() {
+        let amount: Amount = serde_json::from_str(r#""1.5""#).unwrap();
+        assert_eq!(amount.to_string(), "1.5");
+    }
+
+    #[test]
+    fn serializes_as_string() {
+        let fill = Fill {
+            price: "27123.45".parse().unwrap(),
+            size: Amount::from(2i64),
+        };
+        assert_eq!(
+            serde_json::to_string(&fill).unwrap(),
+            r#"{"price":"27123.45","size":"2"}"#
+        );
+        assert_eq!(
+            Value::from(fill.price),
+            Value::String("27123.45".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_non_numeric_json() {
+        assert!(serde_json::from_str::<Amount>("null").is_err());
+        assert!(serde_json::from_str::<Amount>("[1]").is_err());
+        assert!(Amount::try_from(&Value::Bool(true)).is_err());
+        assert_eq!(
+            Amount::try_from(&Value::String("7.25".into())).unwrap(),
+            "7.25".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn formats_with_currency_precision() {
+        let amount: Amount = "1234.505".parse().unwrap();
+        assert_eq!(amount.format_currency("usd"), "1234.50 USD");
+        assert_eq!(amount.format_currency("JPY"), "1235 JPY");
+        assert_eq!(amount.format_currency("XYZ"), "1234.505 XYZ");
+    }
+}
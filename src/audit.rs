@@ -0,0 +1,662 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::har::iso8601;
+use crate::http_client::HttpClient;
+use crate::http_method::HttpMethod;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::http_url::HttpUrl;
+use crate::idempotency::{self, IdempotencyStrategy};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// The `prev_hash` of the first record in a log
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Whether a record was written before the request was sent or after it finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    Request,
+    Outcome,
+}
+
+/// One line of the audit log: a state-changing request about to be sent, or the outcome
+/// it got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the log, starting at 0
+    pub sequence: u64,
+    pub kind: AuditKind,
+    /// For outcome records, the sequence of the request record they complete
+    pub request_sequence: Option<u64>,
+    /// When the record was written, as an ISO 8601 UTC timestamp
+    pub timestamp: String,
+    pub method: String,
+    /// The route the caller requested, without query parameters
+    pub route: String,
+    /// Hex SHA-256 of the body as sent, after any idempotency key was written into it; only
+    /// on request records, and absent for empty and multipart bodies
+    pub body_sha256: Option<String>,
+    /// The idempotency key (or client order id) the request was sent with
+    pub idempotency_key: Option<String>,
+    /// The response status; absent when no response was received
+    pub status: Option<u16>,
+    /// The server's request id, when the client captured one
+    pub request_id: Option<String>,
+    /// Why the request failed without a response
+    pub error: Option<String>,
+    /// The `hash` of the previous record, or [`GENESIS_HASH`] for the first one
+    pub prev_hash: String,
+    /// Hex SHA-256 (or HMAC-SHA-256, for keyed logs) of this record serialized with an empty
+    /// `hash`
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// The hash this record should carry in an unkeyed log, given its other fields
+    pub fn compute_hash(&self) -> String {
+        hex::encode(Sha256::digest(self.unhashed()))
+    }
+
+    /// The hash this record should carry in a log keyed with `key`
+    pub fn compute_hmac(&self, key: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+        mac.update(&self.unhashed());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn expected_hash(&self, key: Option<&[u8]>) -> String {
+        match key {
+            Some(key) => self.compute_hmac(key),
+            None => self.compute_hash(),
+        }
+    }
+
+    fn unhashed(&self) -> Vec<u8> {
+        let unhashed = AuditRecord {
+            hash: String::new(),
+            ..self.clone()
+        };
+        serde_json::to_vec(&unhashed).expect("audit records always serialize")
+    }
+}
+
+/// Attached to a response whose outcome record could not be written. The request record
+/// was written before sending, so the log shows the request without its outcome.
+#[derive(Debug, Clone)]
+pub struct AuditFailure(pub Arc<HttpError>);
+
+struct Chain {
+    file: File,
+    len: u64,
+    next_sequence: u64,
+    last_hash: String,
+}
+
+/// Wraps a client and appends a hash-chained JSON-lines record of every non-GET request
+/// to a local file.
+///
+/// Each request gets two records: one written and synced before it is sent, so a request
+/// is never sent unlogged, and one with the outcome (status, server request id or error)
+/// once it finishes. If the request record can't be written the request is not sent and
+/// the I/O error is returned. If the outcome record can't be written the caller still gets
+/// the outcome: a response carries an [`AuditFailure`] extension, and errors are returned
+/// unchanged with the failure logged through `tracing` when that feature is on. A record
+/// that was only partly written is cut off again so the chain stays intact.
+///
+/// Idempotency keys are assigned here, before the request record is written, so the logged
+/// body hash and key match what the inner client sends: the request's own strategy is
+/// applied, or for POST and PATCH the one given with [`AuditLog::with_idempotency`]. Give
+/// that the same strategy as the inner client's
+/// [`crate::http_client::ReqwestClientBuilder::idempotency`]; the inner client then keeps
+/// the key it finds instead of generating another.
+///
+/// Server request ids are logged when the inner client captures them, as
+/// [`crate::http_client::ReqwestClient`] does (from `X-Request-Id`, or the headers set with
+/// [`crate::http_client::ReqwestClientBuilder::correlation`]).
+///
+/// Each record carries the hash of the one before it, so editing, removing or reordering
+/// records breaks the chain; check it with [`verify`]. Two things a plain SHA-256 chain
+/// can't show: records cut off the end of the file, and a whole file rewritten by someone
+/// who recomputes every hash. Keep the latest sequence and hash somewhere else to detect
+/// the first, and use [`AuditLog::with_hmac_key`] with a key stored away from the log to
+/// rule out the second.
+pub struct AuditLog<C> {
+    inner: C,
+    path: PathBuf,
+    key: Option<Vec<u8>>,
+    idempotency: Option<IdempotencyStrategy>,
+    chain: Mutex<Chain>,
+}
+
+impl<C: HttpClient> AuditLog<C> {
+    /// Open the log at `path`, continuing the chain of any records already in it.
+    ///
+    /// A last line without a trailing newline is what a crash in the middle of a write
+    /// leaves behind, so it is removed and the chain continues from the record before it.
+    pub async fn open(inner: C, path: impl Into<PathBuf>) -> HttpResult<Self> {
+        let path = path.into();
+        let mut next_sequence = 0;
+        let mut last_hash = GENESIS_HASH.to_string();
+        let contents = match tokio::fs::read(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let complete = contents
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let lines = contents[..complete].split(|b| *b == b'\n');
+        if let Some((index, line)) = lines
+            .enumerate()
+            .filter(|(_, line)| !line.trim_ascii().is_empty())
+            .last()
+        {
+            let record: AuditRecord =
+                serde_json::from_slice(line).map_err(|e| HttpError::AuditChain {
+                    line: index as u64 + 1,
+                    reason: format!("malformed record: {e}"),
+                })?;
+            next_sequence = record.sequence + 1;
+            last_hash = record.hash;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        if complete < contents.len() {
+            file.set_len(complete as u64).await?;
+        }
+        Ok(Self {
+            inner,
+            path,
+            key: None,
+            idempotency: None,
+            chain: Mutex::new(Chain {
+                file,
+                len: complete as u64,
+                next_sequence,
+                last_hash,
+            }),
+        })
+    }
+
+    /// Chain records with HMAC-SHA-256 under `key` instead of plain SHA-256; check the log
+    /// with [`verify_with_key`]
+    pub fn with_hmac_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Key POST and PATCH requests without their own strategy with `strategy` before they
+    /// are logged, as the inner client would
+    pub fn with_idempotency(mut self, strategy: IdempotencyStrategy) -> Self {
+        self.idempotency = Some(strategy);
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Write the request record and return its sequence
+    async fn append_request(&self, request: &HttpRequest) -> HttpResult<u64> {
+        self.append(|sequence| AuditRecord {
+            sequence,
+            kind: AuditKind::Request,
+            request_sequence: None,
+            timestamp: iso8601(SystemTime::now()),
+            method: request.method.to_string(),
+            route: route(request),
+            body_sha256: request
+                .body_bytes()
+                .filter(|body| !body.is_empty())
+                .map(|body| hex::encode(Sha256::digest(body))),
+            idempotency_key: request.idempotency_key().map(str::to_string),
+            status: None,
+            request_id: None,
+            error: None,
+            prev_hash: String::new(),
+            hash: String::new(),
+        })
+        .await
+    }
+
+    async fn append_outcome(
+        &self,
+        request_sequence: u64,
+        request: &HttpRequest,
+        result: &HttpResult<HttpResponse>,
+    ) -> HttpResult<u64> {
+        let (status, request_id, error) = match result {
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                response.request_id().map(str::to_string),
+                None,
+            ),
+            Err(e) => (
+                e.status().map(|status| status.as_u16()),
+                e.request_id().map(str::to_string),
+                e.status().is_none().then(|| e.to_string()),
+            ),
+        };
+        self.append(|sequence| AuditRecord {
+            sequence,
+            kind: AuditKind::Outcome,
+            request_sequence: Some(request_sequence),
+            timestamp: iso8601(SystemTime::now()),
+            method: request.method.to_string(),
+            route: route(request),
+            body_sha256: None,
+            idempotency_key: request.idempotency_key().map(str::to_string),
+            status,
+            request_id,
+            error,
+            prev_hash: String::new(),
+            hash: String::new(),
+        })
+        .await
+    }
+
+    async fn append(&self, record: impl FnOnce(u64) -> AuditRecord) -> HttpResult<u64> {
+        let mut chain = self.chain.lock().await;
+        let mut record = AuditRecord {
+            prev_hash: chain.last_hash.clone(),
+            ..record(chain.next_sequence)
+        };
+        record.hash = record.expected_hash(self.key.as_deref());
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let written = match chain.file.write_all(&line).await {
+            Ok(()) => chain.file.sync_data().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            // Cut off a partial line so later records still follow a complete one
+            let len = chain.len;
+            let _ = chain.file.set_len(len).await;
+            return Err(e.into());
+        }
+        chain.len += line.len() as u64;
+        chain.next_sequence += 1;
+        chain.last_hash = record.hash;
+        Ok(record.sequence)
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> HttpClient for AuditLog<C> {
+    async fn execute(&self, mut request: HttpRequest) -> HttpResult<HttpResponse> {
+        if request.method == HttpMethod::Get {
+            return self.inner.execute(request).await;
+        }
+        idempotency::assign(&mut request, self.idempotency.as_ref())?;
+        let recorded = request.clone();
+        let sequence = self.append_request(&recorded).await?;
+        let result = self.inner.execute(request).await;
+        match (
+            self.append_outcome(sequence, &recorded, &result).await,
+            result,
+        ) {
+            (Ok(_), result) => result,
+            (Err(e), Ok(response)) => Ok(response.with_extension(AuditFailure(Arc::new(e)))),
+            (Err(_e), Err(error)) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(path = %self.path.display(), error = %_e, "failed to write audit outcome record");
+                Err(error)
+            }
+        }
+    }
+}
+
+// The caller's route without query, falling back to the resolved URL path
+fn route(request: &HttpRequest) -> String {
+    match &request.path {
+        Some(path) if HttpUrl::is_absolute(path) => url::Url::parse(path)
+            .map(|url| url.path().to_string())
+            .unwrap_or_else(|_| path.clone()),
+        Some(path) => path.split('?').next().unwrap_or_default().to_string(),
+        None => request.get_url_path().to_string(),
+    }
+}
+
+/// Check the hash chain of an audit log file and return the number of records in it.
+///
+/// Fails with [`HttpError::AuditChain`] at the first record that doesn't parse, is out of
+/// sequence, doesn't link to the previous record or doesn't match its own hash.
+pub fn verify(path: impl AsRef<Path>) -> HttpResult<u64> {
+    let file = std::fs::File::open(path)?;
+    verify_reader(std::io::BufReader::new(file))
+}
+
+/// [`verify`] for a log written with [`AuditLog::with_hmac_key`]
+pub fn verify_with_key(path: impl AsRef<Path>, key: &[u8]) -> HttpResult<u64> {
+    let file = std::fs::File::open(path)?;
+    check_chain(std::io::BufReader::new(file), Some(key))
+}
+
+/// Check the hash chain of audit records read line by line from `reader`
+pub fn verify_reader(reader: impl BufRead) -> HttpResult<u64> {
+    check_chain(reader, None)
+}
+
+fn check_chain(reader: impl BufRead, key: Option<&[u8]>) -> HttpResult<u64> {
+    let mut expected_sequence = 0;
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let broken = |reason: String| HttpError::AuditChain {
+            line: index as u64 + 1,
+            reason,
+        };
+        let record: AuditRecord =
+            serde_json::from_str(&line).map_err(|e| broken(format!("malformed record: {e}")))?;
+        if record.sequence != expected_sequence {
+            return Err(broken(format!(
+                "expected sequence {expected_sequence}, found {}",
+                record.sequence
+            )));
+        }
+        if record.prev_hash != prev_hash {
+            return Err(broken("does not link to the previous record".to_string()));
+        }
+        if record.expected_hash(key) != record.hash {
+            return Err(broken(
+                "record hash does not match its contents".to_string(),
+            ));
+        }
+        expected_sequence += 1;
+        prev_hash = record.hash;
+    }
+    Ok(expected_sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_status_code::HttpStatusCode;
+
+    // Answers every request with 201, or fails without a response when the path is `/down`
+    struct Server;
+
+    #[async_trait]
+    impl HttpClient for Server {
+        async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+            match request.path.as_deref() {
+                Some("/down") => Err(HttpError::Config("connection refused".to_string())),
+                _ => Ok(HttpResponse::new(HttpStatusCode::Created)),
+            }
+        }
+    }
+
+    // Answers every request with 201 and keeps the bodies it was sent
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<Vec<u8>>>);
+
+    #[async_trait]
+    impl HttpClient for Recorder {
+        async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(request.body_bytes().unwrap_or_default());
+            Ok(HttpResponse::new(HttpStatusCode::Created))
+        }
+    }
+
+    fn temp_log() -> PathBuf {
+        std::env::temp_dir().join(format!("core-rs-audit-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    fn records(path: &Path) -> Vec<AuditRecord> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn order(path: &str) -> HttpRequest {
+        HttpRequest::new(HttpMethod::Post, path)
+            .unwrap()
+            .with_body(r#"{"side":"BUY"}"#)
+    }
+
+    async fn write_log(path: &Path, requests: usize) {
+        let log = AuditLog::open(Server, path).await.unwrap();
+        for _ in 0..requests {
+            log.execute(order("/v1/orders?portfolio=p")).await.unwrap();
+        }
+    }
+
+    fn lines(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn chain_error(lines: &[String]) -> (u64, String) {
+        let contents = lines.join("\n");
+        match verify_reader(contents.as_bytes()) {
+            Err(HttpError::AuditChain { line, reason }) => (line, reason),
+            other => panic!("expected a broken chain, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_a_request_record_then_an_outcome_record() {
+        let path = temp_log();
+        let log = AuditLog::open(Server, &path).await.unwrap();
+        log.execute(order("/v1/orders?portfolio=p")).await.unwrap();
+        assert!(log.execute(order("/down")).await.is_err());
+        log.execute(HttpRequest::new(HttpMethod::Get, "/v1/orders").unwrap())
+            .await
+            .unwrap();
+
+        let records = records(&path);
+        assert_eq!(records.len(), 4);
+        let (request, outcome) = (&records[0], &records[1]);
+        assert_eq!(request.kind, AuditKind::Request);
+        assert_eq!(request.route, "/v1/orders");
+        assert_eq!(
+            request.body_sha256.as_deref(),
+            Some(hex::encode(Sha256::digest(br#"{"side":"BUY"}"#)).as_str())
+        );
+        assert_eq!(request.status, None);
+        assert_eq!(request.prev_hash, GENESIS_HASH);
+        assert_eq!(outcome.kind, AuditKind::Outcome);
+        assert_eq!(outcome.request_sequence, Some(0));
+        assert_eq!(outcome.status, Some(201));
+        assert_eq!(outcome.body_sha256, None);
+        assert_eq!(outcome.prev_hash, request.hash);
+
+        assert_eq!(records[3].request_sequence, Some(2));
+        assert_eq!(records[3].status, None);
+        assert!(records[3]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("connection refused"));
+        assert_eq!(verify(&path).unwrap(), 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // The logged hash and key are those of the body the inner client received
+    fn assert_logged_as_sent(path: &Path, sent: &[u8]) {
+        let sent_json: serde_json::Value = serde_json::from_slice(sent).unwrap();
+        let client_order_id = sent_json["client_order_id"].as_str().unwrap();
+        let records = records(path);
+        assert_eq!(
+            records[0].body_sha256.as_deref(),
+            Some(hex::encode(Sha256::digest(sent)).as_str())
+        );
+        assert_eq!(records[0].idempotency_key.as_deref(), Some(client_order_id));
+        assert_eq!(records[1].idempotency_key.as_deref(), Some(client_order_id));
+    }
+
+    #[tokio::test]
+    async fn logs_the_body_with_its_client_order_id() {
+        let path = temp_log();
+        let log = AuditLog::open(Recorder::default(), &path).await.unwrap();
+        let request = HttpRequest::new(HttpMethod::Post, "/v1/orders")
+            .unwrap()
+            .with_json_body(serde_json::json!({"side": "BUY"}))
+            .with_idempotency(IdempotencyStrategy::client_order_id());
+        log.execute(request).await.unwrap();
+
+        let sent = log.inner().0.lock().unwrap().pop().unwrap();
+        assert_logged_as_sent(&path, &sent);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn applies_its_default_strategy_before_logging() {
+        let path = temp_log();
+        let log = AuditLog::open(Recorder::default(), &path)
+            .await
+            .unwrap()
+            .with_idempotency(IdempotencyStrategy::client_order_id());
+        let order = HttpRequest::new(HttpMethod::Post, "/v1/orders")
+            .unwrap()
+            .with_json_body(serde_json::json!({"side": "BUY"}));
+        log.execute(order).await.unwrap();
+        log.execute(HttpRequest::new(HttpMethod::Delete, "/v1/orders/1").unwrap())
+            .await
+            .unwrap();
+
+        let sent = log.inner().0.lock().unwrap()[0].clone();
+        assert_logged_as_sent(&path, &sent);
+        assert_eq!(records(&path)[2].idempotency_key, None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reopening_continues_the_chain() {
+        let path = temp_log();
+        write_log(&path, 1).await;
+        write_log(&path, 1).await;
+        let records = records(&path);
+        assert_eq!(records[2].sequence, 2);
+        assert_eq!(records[2].prev_hash, records[1].hash);
+        assert_eq!(verify(&path).unwrap(), 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn open_drops_a_torn_last_line() {
+        let path = temp_log();
+        write_log(&path, 1).await;
+        let mut contents = std::fs::read_to_string(&path).unwrap();
+        contents.push_str(r#"{"sequence":2,"kind":"req"#);
+        std::fs::write(&path, contents).unwrap();
+
+        write_log(&path, 1).await;
+        assert_eq!(verify(&path).unwrap(), 4);
+        assert_eq!(records(&path)[2].sequence, 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn open_rejects_a_malformed_complete_line() {
+        let path = temp_log();
+        write_log(&path, 1).await;
+        let mut contents = std::fs::read_to_string(&path).unwrap();
+        contents.push_str("not a record\n");
+        std::fs::write(&path, contents).unwrap();
+
+        match AuditLog::open(Server, &path).await {
+            Err(HttpError::AuditChain { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected a broken chain, got {:?}", other.err()),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn verify_reader_detects_tampering() {
+        let path = temp_log();
+        write_log(&path, 2).await;
+        let original = lines(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(verify_reader(original.join("\n").as_bytes()).unwrap(), 4);
+
+        let mut edited = original.clone();
+        edited[1] = edited[1].replace("201", "500");
+        assert_eq!(chain_error(&edited).0, 2);
+        assert!(chain_error(&edited).1.contains("hash does not match"));
+
+        let mut reordered = original.clone();
+        reordered.swap(2, 3);
+        assert!(chain_error(&reordered).1.contains("expected sequence 2"));
+
+        let mut removed = original.clone();
+        removed.remove(1);
+        assert!(chain_error(&removed).1.contains("expected sequence 1"));
+
+        // A record rehashed after editing still fails to link from the next one
+        let mut rehashed = original.clone();
+        let mut record: AuditRecord = serde_json::from_str(&rehashed[1]).unwrap();
+        record.status = Some(500);
+        record.hash = record.compute_hash();
+        rehashed[1] = serde_json::to_string(&record).unwrap();
+        let (line, reason) = chain_error(&rehashed);
+        assert_eq!(line, 3);
+        assert!(reason.contains("does not link"));
+
+        let mut malformed = original;
+        malformed.insert(2, "{".to_string());
+        assert!(chain_error(&malformed).1.contains("malformed record"));
+    }
+
+    #[tokio::test]
+    async fn keyed_logs_verify_only_with_their_key() {
+        let path = temp_log();
+        let log = AuditLog::open(Server, &path)
+            .await
+            .unwrap()
+            .with_hmac_key(b"secret".to_vec());
+        log.execute(order("/v1/orders")).await.unwrap();
+
+        assert_eq!(verify_with_key(&path, b"secret").unwrap(), 2);
+        assert!(matches!(
+            verify_with_key(&path, b"guess"),
+            Err(HttpError::AuditChain { line: 1, .. })
+        ));
+        assert!(verify(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::HttpResult;
+use crate::http_client::HttpClient;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::http_status_code::HttpStatusCode;
+use crate::rate_limit::RateLimiter;
+use futures_util::future::{self, FutureExt};
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOrder {
+    /// Yield results in the order the requests were given
+    Ordered,
+    /// Yield results as soon as each request finishes
+    Completion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Stop after the first failed request; requests still in flight are cancelled
+    FailFast,
+    /// Run every request and report each outcome
+    CollectAll,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    pub concurrency: usize,
+    pub order: BatchOrder,
+    pub mode: BatchMode,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// How long the rate limiter holds back the batch after a 429 without `Retry-After`
+    pub throttle_pause: Duration,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            order: BatchOrder::Ordered,
+            mode: BatchMode::CollectAll,
+            rate_limiter: None,
+            throttle_pause: Duration::from_secs(1),
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_order(mut self, order: BatchOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: BatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Take a token before sending each request, and pause the limiter when a request
+    /// is answered with 429, for as long as its `Retry-After` asks
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    pub fn with_throttle_pause(mut self, pause: Duration) -> Self {
+        self.throttle_pause = pause;
+        self
+    }
+}
+
+/// The outcome of one request in a batch, with its position in the input
+#[derive(Debug)]
+pub struct BatchItem {
+    pub index: usize,
+    pub result: HttpResult<HttpResponse>,
+}
+
+/// Run many requests through a client with bounded concurrency
+pub trait BatchExt: HttpClient {
+    /// Stream the outcome of each request. In fail-fast mode the stream ends after the
+    /// first failure.
+    fn execute_batch<'a, I>(
+        &'a self,
+        requests: I,
+        options: BatchOptions,
+    ) -> BoxStream<'a, BatchItem>
+    where
+        I: IntoIterator<Item = HttpRequest>,
+        I::IntoIter: Send + 'a,
+    {
+        let limiter = options.rate_limiter.clone();
+        let throttle_pause = options.throttle_pause;
+        let calls = stream::iter(requests.into_iter().enumerate()).map(move |(index, request)| {
+            let limiter = limiter.clone();
+            async move {
+                if let Some(limiter) = &limiter {
+                    limiter.acquire().await;
+                }
+                let result = self.execute(request).await;
+                if let (Some(limiter), Err(e)) = (&limiter, &result) {
+                    if e.status() == Some(HttpStatusCode::TooManyRequests) {
+                        let retry_after = e.response_headers().and_then(|h| h.retry_after());
+                        limiter.pause(retry_after.unwrap_or(throttle_pause));
+                    }
+                }
+                BatchItem { index, result }
+            }
+        });
+        let concurrency = options.concurrency.max(1);
+        let items = match options.order {
+            BatchOrder::Ordered => calls.buffered(concurrency).boxed(),
+            BatchOrder::Completion => calls.buffer_unordered(concurrency).boxed(),
+        };
+        match options.mode {
+            BatchMode::CollectAll => items,
+            BatchMode::FailFast => items
+                .scan(false, |failed, item| {
+                    if *failed {
+                        return future::ready(None);
+                    }
+                    *failed = item.result.is_err();
+                    future::ready(Some(item))
+                })
+                .boxed(),
+        }
+    }
+
+    /// Run every request and return the responses in input order, stopping at the first error
+    fn execute_all<'a, I>(
+        &'a self,
+        requests: I,
+        concurrency: usize,
+    ) -> future::BoxFuture<'a, HttpResult<Vec<HttpResponse>>>
+    where
+        I: IntoIterator<Item = HttpRequest>,
+        I::IntoIter: Send + 'a,
+    {
+        let options = BatchOptions::new()
+            .with_concurrency(concurrency)
+            .with_mode(BatchMode::FailFast);
+        self.execute_batch(requests, options)
+            .map(|item| item.result)
+            .try_collect()
+            .boxed()
+    }
+}
+
+impl<C: HttpClient + ?Sized> BatchExt for C {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::HttpError;
+    use crate::http_headers::HttpHeaders;
+    use crate::http_method::HttpMethod;
+    use async_trait::async_trait;
+
+    // Answers `/fail` with 500, `/throttled` with 429 and everything else with 200
+    struct Server {
+        retry_after: Option<&'static str>,
+    }
+
+    #[async_trait]
+    impl HttpClient for Server {
+        async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+            let status = match request.path.as_deref() {
+                Some("/fail") => HttpStatusCode::InternalServerError,
+                Some("/throttled") => HttpStatusCode::TooManyRequests,
+                _ => return Ok(HttpResponse::new(HttpStatusCode::Ok)),
+            };
+            let mut headers = HttpHeaders::new();
+            if let Some(retry_after) = self.retry_after {
+                headers.insert("retry-after", retry_after)?;
+            }
+            Err(HttpError::Status {
+                status,
+                headers,
+                body: Default::default(),
+            })
+        }
+    }
+
+    fn requests(paths: &[&str]) -> Vec<HttpRequest> {
+        paths
+            .iter()
+            .map(|path| HttpRequest::new(HttpMethod::Get, path).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn collect_all_keeps_input_order_and_fail_fast_stops() {
+        let server = Server { retry_after: None };
+        let items: Vec<_> = server
+            .execute_batch(requests(&["/a", "/fail", "/b"]), BatchOptions::new())
+            .collect()
+            .await;
+        let outcome: Vec<_> = items.iter().map(|i| (i.index, i.result.is_ok())).collect();
+        assert_eq!(outcome, [(0, true), (1, false), (2, true)]);
+
+        let options = BatchOptions::new()
+            .with_concurrency(1)
+            .with_mode(BatchMode::FailFast);
+        let items: Vec<_> = server
+            .execute_batch(requests(&["/a", "/fail", "/b"]), options)
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+        assert!(server
+            .execute_all(requests(&["/a", "/fail"]), 4)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn throttling_pauses_the_limiter_for_retry_after() {
+        let limiter = Arc::new(RateLimiter::new(1000.0, 10).unwrap());
+        let options = BatchOptions::new()
+            .with_rate_limiter(limiter.clone())
+            .with_throttle_pause(Duration::ZERO);
+        let server = Server {
+            retry_after: Some("1"),
+        };
+        let _: Vec<_> = server
+            .execute_batch(requests(&["/throttled"]), options.clone())
+            .collect()
+            .await;
+        let blocked = tokio::time::timeout(Duration::from_millis(100), limiter.acquire()).await;
+        assert!(blocked.is_err(), "Retry-After should hold the limiter");
+
+        let limiter = Arc::new(RateLimiter::new(1000.0, 10).unwrap());
+        let server = Server { retry_after: None };
+        let _: Vec<_> = server
+            .execute_batch(
+                requests(&["/throttled"]),
+                options.with_rate_limiter(limiter.clone()),
+            )
+            .collect()
+            .await;
+        let free = tokio::time::timeout(Duration::from_millis(100), limiter.acquire()).await;
+        assert!(
+            free.is_ok(),
+            "without Retry-After the configured pause applies"
+        );
+    }
+}
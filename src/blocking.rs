@@ -0,0 +1,151 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_client::{HttpClient, ReqwestClient, ReqwestClientBuilder};
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use tokio::runtime::{Builder, Runtime};
+
+/// A synchronous client for code that isn't async, e.g. CLI tools and batch scripts.
+///
+/// Requests run on a private tokio runtime through the wrapped async client, so the
+/// builder, interceptors, retries, signing and middleware all behave as they do in async
+/// code. Calls fail with [`HttpError::Config`] when made from inside an async runtime;
+/// use the async client there instead.
+pub struct BlockingClient<C = ReqwestClient> {
+    inner: C,
+    runtime: Option<Runtime>,
+}
+
+impl BlockingClient {
+    /// Configure the wrapped client, then call [`ReqwestClientBuilder::build_blocking`]
+    pub fn builder() -> ReqwestClientBuilder {
+        ReqwestClient::builder()
+    }
+}
+
+impl<C: HttpClient> BlockingClient<C> {
+    /// Wrap an async client, including middleware such as `Cached` or `Hedged`
+    pub fn from_client(inner: C) -> HttpResult<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("core-rs-blocking")
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            inner,
+            runtime: Some(runtime),
+        })
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Send a request and wait for the response, with its body read into memory
+    pub fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+        self.block_on(async {
+            let response = self.inner.execute(request).await?;
+            response.buffer().await
+        })?
+    }
+
+    /// Send a request and decode its JSON body
+    pub fn execute_json<T: DeserializeOwned>(&self, request: HttpRequest) -> HttpResult<T> {
+        self.block_on(async { self.inner.execute(request).await?.json().await })?
+    }
+
+    /// Run any future on the client's runtime, e.g. a download or a batch
+    pub fn block_on<F: Future>(&self, future: F) -> HttpResult<F::Output> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(HttpError::Config(
+                "BlockingClient can't be used from inside an async runtime".to_string(),
+            ));
+        }
+        match &self.runtime {
+            Some(runtime) => Ok(runtime.block_on(future)),
+            None => Err(HttpError::Config(
+                "BlockingClient runtime is gone".to_string(),
+            )),
+        }
+    }
+}
+
+// Shutting down in the background never blocks, so the client can be dropped anywhere
+impl<C> Drop for BlockingClient<C> {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl ReqwestClientBuilder {
+    /// Build a [`BlockingClient`] with these settings
+    pub fn build_blocking(self) -> HttpResult<BlockingClient> {
+        BlockingClient::from_client(self.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_method::HttpMethod;
+    use crate::http_status_code::HttpStatusCode;
+    use async_trait::async_trait;
+    use std::time::Duration;
+
+    // Needs a tokio timer, so it only works when driven by the client's runtime
+    struct Server;
+
+    #[async_trait]
+    impl HttpClient for Server {
+        async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            Ok(HttpResponse::new(HttpStatusCode::Ok).with_body(format!(
+                r#"{{"path":"{}"}}"#,
+                request.path.unwrap_or_default()
+            )))
+        }
+    }
+
+    fn get() -> HttpRequest {
+        HttpRequest::new(HttpMethod::Get, "/orders").unwrap()
+    }
+
+    #[test]
+    fn executes_outside_a_runtime() {
+        let client = BlockingClient::from_client(Server).unwrap();
+        let response = client.execute(get()).unwrap();
+        assert_eq!(response.status(), HttpStatusCode::Ok);
+        let body: serde_json::Value = client.execute_json(get()).unwrap();
+        assert_eq!(body["path"], "/orders");
+    }
+
+    #[tokio::test]
+    async fn inside_a_runtime_is_a_config_error() {
+        let client = BlockingClient::from_client(Server).unwrap();
+        assert!(matches!(client.execute(get()), Err(HttpError::Config(_))));
+        assert!(matches!(
+            client.execute_json::<serde_json::Value>(get()),
+            Err(HttpError::Config(_))
+        ));
+        // dropping it here must not panic either
+        drop(client);
+    }
+}
@@ -0,0 +1,625 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::HttpResult;
+use crate::extensions::Extensions;
+use crate::http_client::HttpClient;
+use crate::http_headers::HttpHeaders;
+use crate::http_method::HttpMethod;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::http_status_code::HttpStatusCode;
+use crate::http_url::HttpUrl;
+use async_trait::async_trait;
+use base64::Engine;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How a response was served by [`Cached`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// Fetched from the origin
+    Miss,
+    /// Served from the cache while still fresh
+    Hit,
+    /// The origin answered `304 Not Modified` and the cached body was served
+    Revalidated,
+}
+
+impl CacheStatus {
+    /// Whether the body came from the cache
+    pub fn is_hit(&self) -> bool {
+        matches!(self, CacheStatus::Hit | CacheStatus::Revalidated)
+    }
+}
+
+/// A stored response and what is needed to decide whether it can be reused
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub response: HttpResponse,
+    pub stored_at: SystemTime,
+    /// Request header values the response varies on, by lowercase header name
+    pub vary: Vec<(String, Option<String>)>,
+    /// Whether the request carried credentials (`Authorization`, `Cookie` or `CB-ACCESS-*`)
+    pub authenticated: bool,
+}
+
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, key: &str) -> Option<CacheEntry>;
+    async fn put(&self, key: &str, entry: CacheEntry);
+    async fn remove(&self, key: &str);
+}
+
+/// An in-memory store that evicts the least recently used entry when full
+pub struct MemoryStore {
+    capacity: usize,
+    entries: Mutex<HashMap<String, (CacheEntry, u64)>>,
+    clock: AtomicU64,
+}
+
+impl MemoryStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl CacheStore for MemoryStore {
+    async fn get(&self, key: &str) -> Option<CacheEntry> {
+        let tick = self.tick();
+        let mut entries = self.entries.lock().unwrap();
+        let (entry, used) = entries.get_mut(key)?;
+        *used = tick;
+        Some(entry.clone())
+    }
+
+    async fn put(&self, key: &str, entry: CacheEntry) {
+        let tick = self.tick();
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(key) && entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key.to_string(), (entry, tick));
+    }
+
+    async fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+/// A store that keeps one JSON file per entry in a directory, surviving restarts.
+///
+/// Files are named by a hash of the cache key. Responses to authenticated requests are
+/// private to the credentials that fetched them and are not written to disk unless
+/// [`DiskStore::with_authenticated_responses`] allows it.
+pub struct DiskStore {
+    dir: PathBuf,
+    authenticated_responses: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    status: u16,
+    url: Option<String>,
+    headers: Vec<(String, String)>,
+    body: String,
+    stored_at: u64,
+    vary: Vec<(String, Option<String>)>,
+    #[serde(default)]
+    authenticated: bool,
+}
+
+impl DiskStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            authenticated_responses: false,
+        }
+    }
+
+    /// Also persist responses to requests that carried credentials (off by default)
+    pub fn with_authenticated_responses(mut self, enabled: bool) -> Self {
+        self.authenticated_responses = enabled;
+        self
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", hex::encode(Sha256::digest(key))))
+    }
+
+    fn decode(entry: DiskEntry) -> Option<CacheEntry> {
+        let mut headers = HttpHeaders::new();
+        for (name, value) in entry.headers {
+            headers.append(name, value).ok()?;
+        }
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(entry.body)
+            .ok()?;
+        let mut response = HttpResponse::from_parts(
+            HttpStatusCode::from(entry.status),
+            headers,
+            Bytes::from(body),
+        );
+        if let Some(url) = entry.url {
+            response = response.with_url(HttpUrl::parse(&url).ok()?);
+        }
+        Some(CacheEntry {
+            response,
+            stored_at: UNIX_EPOCH + Duration::from_secs(entry.stored_at),
+            vary: entry.vary,
+            authenticated: entry.authenticated,
+        })
+    }
+
+    fn encode(entry: &CacheEntry) -> Option<DiskEntry> {
+        Some(DiskEntry {
+            status: entry.response.status().as_u16(),
+            url: entry.response.url().map(ToString::to_string),
+            headers: entry
+                .response
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: base64::engine::general_purpose::STANDARD.encode(entry.response.body()?),
+            stored_at: entry
+                .stored_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            vary: entry.vary.clone(),
+            authenticated: entry.authenticated,
+        })
+    }
+}
+
+#[async_trait]
+impl CacheStore for DiskStore {
+    async fn get(&self, key: &str) -> Option<CacheEntry> {
+        let contents = tokio::fs::read(self.path(key)).await.ok()?;
+        Self::decode(serde_json::from_slice(&contents).ok()?)
+    }
+
+    // The cache is best effort, so write failures only mean a later miss
+    async fn put(&self, key: &str, entry: CacheEntry) {
+        if entry.authenticated && !self.authenticated_responses {
+            return;
+        }
+        let Some(Ok(contents)) = Self::encode(&entry).map(|e| serde_json::to_vec(&e)) else {
+            return;
+        };
+        let path = self.path(key);
+        let temp = path.with_extension("tmp");
+        if tokio::fs::create_dir_all(&self.dir).await.is_err()
+            || tokio::fs::write(&temp, contents).await.is_err()
+            || tokio::fs::rename(&temp, &path).await.is_err()
+        {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+    }
+
+    async fn remove(&self, key: &str) {
+        let _ = tokio::fs::remove_file(self.path(key)).await;
+    }
+}
+
+/// Wraps a client with a private HTTP cache (RFC 9111) for GET requests.
+///
+/// Fresh entries are served without a request. Stale entries with an `ETag` or
+/// `Last-Modified` are revalidated with `If-None-Match` / `If-Modified-Since`, and a
+/// `304 Not Modified` is returned to the caller as the cached response with
+/// [`CacheStatus::Revalidated`]. Successful non-GET requests invalidate the cached entry
+/// for the same path. `Set-Cookie` headers are never stored.
+pub struct Cached<C> {
+    inner: C,
+    memory: MemoryStore,
+    store: Option<Box<dyn CacheStore>>,
+}
+
+impl<C: HttpClient> Cached<C> {
+    /// Cache up to `capacity` responses in memory
+    pub fn new(inner: C, capacity: usize) -> Self {
+        Self {
+            inner,
+            memory: MemoryStore::new(capacity),
+            store: None,
+        }
+    }
+
+    /// Back the in-memory cache with a persistent store
+    pub fn with_store(mut self, store: impl CacheStore + 'static) -> Self {
+        self.store = Some(Box::new(store));
+        self
+    }
+
+    pub fn with_disk_store(self, dir: impl Into<PathBuf>) -> Self {
+        self.with_store(DiskStore::new(dir))
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    async fn lookup(&self, key: &str) -> Option<CacheEntry> {
+        if let Some(entry) = self.memory.get(key).await {
+            return Some(entry);
+        }
+        let entry = self.store.as_ref()?.get(key).await?;
+        self.memory.put(key, entry.clone()).await;
+        Some(entry)
+    }
+
+    async fn store(&self, key: &str, entry: CacheEntry) {
+        if let Some(store) = &self.store {
+            store.put(key, entry.clone()).await;
+        }
+        self.memory.put(key, entry).await;
+    }
+
+    async fn invalidate(&self, key: &str) {
+        self.memory.remove(key).await;
+        if let Some(store) = &self.store {
+            store.remove(key).await;
+        }
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> HttpClient for Cached<C> {
+    async fn execute(&self, mut request: HttpRequest) -> HttpResult<HttpResponse> {
+        let key = cache_key(&request);
+        let method = request.method.clone();
+        if method != HttpMethod::Get {
+            let response = self.inner.execute(request).await?;
+            if !matches!(method, HttpMethod::Head | HttpMethod::Options) {
+                self.invalidate(&key).await;
+            }
+            return Ok(response);
+        }
+
+        let request_directives = directives(request.headers.get_joined("cache-control").as_deref());
+        if request_directives.contains_key("no-store") {
+            return self.inner.execute(request).await;
+        }
+
+        let cached = self
+            .lookup(&key)
+            .await
+            .filter(|entry| vary_matches(entry, &request.headers));
+        if let Some(entry) = &cached {
+            if !request_directives.contains_key("no-cache") && is_fresh(entry) {
+                return Ok(entry
+                    .response
+                    .clone()
+                    .with_elapsed(Duration::ZERO)
+                    .with_attempts(0)
+                    .with_extensions(request.extensions().clone())
+                    .with_cache_status(CacheStatus::Hit));
+            }
+            if let Some(etag) = entry.response.header("etag") {
+                let _ = request.add_header("if-none-match", etag);
+            }
+            if let Some(modified) = entry.response.header("last-modified") {
+                let _ = request.add_header("if-modified-since", modified);
+            }
+        }
+
+        let request_headers = request.headers.clone();
+        let response = self.inner.execute(request).await?;
+
+        if response.status() == HttpStatusCode::NotModified {
+            if let Some(mut entry) = cached {
+                // Freshness information in the 304 replaces what was stored
+                let mut fresh = response.headers().clone();
+                fresh.remove("content-length");
+                fresh.remove("set-cookie");
+                entry.response.headers_mut().extend(fresh);
+                entry.stored_at = SystemTime::now();
+                let served = entry
+                    .response
+                    .clone()
+                    .with_elapsed(response.elapsed())
+                    .with_attempts(response.attempts())
+                    .with_extensions(response.extensions().clone());
+                self.store(&key, entry).await;
+                return Ok(served.with_cache_status(CacheStatus::Revalidated));
+            }
+            return Ok(response.with_cache_status(CacheStatus::Miss));
+        }
+
+        if !is_storable(&response) {
+            return Ok(response.with_cache_status(CacheStatus::Miss));
+        }
+        let response = response.buffer().await?;
+        let vary = vary_values(&response, &request_headers);
+        // The inner client may have added credentials (e.g. signing headers) of its own
+        let authenticated = carries_credentials(&request_headers)
+            || response.request_headers().is_some_and(carries_credentials);
+        // Extensions and request headers belong to the request that stored the entry
+        let mut stored = response
+            .clone()
+            .with_extensions(Extensions::new())
+            .with_request_headers(None);
+        stored.headers_mut().remove("set-cookie");
+        self.store(
+            &key,
+            CacheEntry {
+                response: stored,
+                stored_at: SystemTime::now(),
+                vary,
+                authenticated,
+            },
+        )
+        .await;
+        Ok(response.with_cache_status(CacheStatus::Miss))
+    }
+}
+
+// Requests are keyed before the inner client resolves them against its base URL
+fn cache_key(request: &HttpRequest) -> String {
+    let mut query: Vec<(&String, &String)> = request.query_params.iter().flatten().collect();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&");
+    format!(
+        "{} {}?{}",
+        request
+            .url
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        request.path.as_deref().unwrap_or_default(),
+        query
+    )
+}
+
+// Cache-Control directives by lowercase name, with their unquoted argument if any
+fn directives(cache_control: Option<&str>) -> HashMap<String, Option<String>> {
+    cache_control
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|directive| {
+            let mut parts = directive.splitn(2, '=');
+            let name = parts.next()?.trim().to_ascii_lowercase();
+            if name.is_empty() {
+                return None;
+            }
+            let value = parts.next().map(|v| v.trim().trim_matches('"').to_string());
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn seconds(directives: &HashMap<String, Option<String>>, name: &str) -> Option<u64> {
+    directives.get(name)?.as_deref()?.parse().ok()
+}
+
+fn http_date(response: &HttpResponse, name: &str) -> Option<SystemTime> {
+    httpdate::parse_http_date(response.header(name)?).ok()
+}
+
+fn is_storable(response: &HttpResponse) -> bool {
+    if !matches!(
+        response.status().as_u16(),
+        200 | 203 | 204 | 300 | 301 | 404 | 410
+    ) {
+        return false;
+    }
+    let vary = response.headers().get_joined("vary").unwrap_or_default();
+    if vary.split(',').any(|name| name.trim() == "*") {
+        return false;
+    }
+    let directives = directives(response.headers().get_joined("cache-control").as_deref());
+    if directives.contains_key("no-store") {
+        return false;
+    }
+    directives.contains_key("max-age")
+        || directives.contains_key("no-cache")
+        || response.header("expires").is_some()
+        || response.header("etag").is_some()
+        || response.header("last-modified").is_some()
+}
+
+fn is_fresh(entry: &CacheEntry) -> bool {
+    let response = &entry.response;
+    let directives = directives(response.headers().get_joined("cache-control").as_deref());
+    if directives.contains_key("no-cache") {
+        return false;
+    }
+    let lifetime = match seconds(&directives, "max-age") {
+        Some(max_age) => Duration::from_secs(max_age),
+        None => match http_date(response, "expires") {
+            Some(expires) => {
+                let date = http_date(response, "date").unwrap_or(entry.stored_at);
+                expires.duration_since(date).unwrap_or_default()
+            }
+            None => Duration::ZERO,
+        },
+    };
+    let age_header = response
+        .header("age")
+        .and_then(|v| v.trim().parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_default();
+    let resident = SystemTime::now()
+        .duration_since(entry.stored_at)
+        .unwrap_or_default();
+    age_header + resident < lifetime
+}
+
+fn vary_values(response: &HttpResponse, request: &HttpHeaders) -> Vec<(String, Option<String>)> {
+    response
+        .headers()
+        .get_joined("vary")
+        .unwrap_or_default()
+        .split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let value = request.get_joined(&name);
+            (name, value)
+        })
+        .collect()
+}
+
+fn carries_credentials(headers: &HttpHeaders) -> bool {
+    headers.names().iter().any(|name| {
+        matches!(name.as_str(), "authorization" | "cookie")
+            || name.starts_with("cb-access-")
+            || name.starts_with("x-cb-access-")
+    })
+}
+
+fn vary_matches(entry: &CacheEntry, request: &HttpHeaders) -> bool {
+    entry
+        .vary
+        .iter()
+        .all(|(name, value)| request.get_joined(name) == *value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    // Serves `/assets` with a one-hour max-age and an ETag, answering conditional requests
+    // with 304
+    #[derive(Default)]
+    struct Origin {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl HttpClient for Origin {
+        async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if request.headers.get("if-none-match") == Some("\"v1\"") {
+                return Ok(HttpResponse::new(HttpStatusCode::NotModified)
+                    .with_header("cache-control", "max-age=3600"));
+            }
+            let cache_control = request
+                .headers
+                .get("x-test-cache-control")
+                .unwrap_or("max-age=3600")
+                .to_string();
+            Ok(HttpResponse::new(HttpStatusCode::Ok)
+                .with_header("cache-control", cache_control)
+                .with_header("etag", "\"v1\"")
+                .with_header("set-cookie", "session=abc")
+                .with_body("[\"BTC\"]"))
+        }
+    }
+
+    fn get() -> HttpRequest {
+        HttpRequest::new(HttpMethod::Get, "/assets").unwrap()
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("core_rs-cache-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn files(dir: &PathBuf) -> Vec<String> {
+        std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn fresh_entries_are_hits_without_set_cookie() {
+        let client = Cached::new(Origin::default(), 8);
+        let first = client.execute(get()).await.unwrap();
+        assert_eq!(first.cache_status(), Some(CacheStatus::Miss));
+        assert_eq!(first.header("set-cookie"), Some("session=abc"));
+
+        let second = client.execute(get()).await.unwrap();
+        assert_eq!(second.cache_status(), Some(CacheStatus::Hit));
+        assert_eq!(second.header("set-cookie"), None);
+        assert_eq!(second.body().unwrap().as_ref(), b"[\"BTC\"]");
+        assert_eq!(client.inner().calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn stale_entries_are_revalidated_with_the_etag() {
+        let client = Cached::new(Origin::default(), 8);
+        let request = get().with_header("x-test-cache-control", "no-cache");
+        client.execute(request).await.unwrap();
+        let revalidated = client.execute(get()).await.unwrap();
+        assert_eq!(revalidated.cache_status(), Some(CacheStatus::Revalidated));
+        assert_eq!(revalidated.status(), HttpStatusCode::Ok);
+        assert_eq!(revalidated.body().unwrap().as_ref(), b"[\"BTC\"]");
+    }
+
+    #[tokio::test]
+    async fn disk_store_skips_authenticated_responses_unless_allowed() {
+        let dir = temp_dir();
+        let client = Cached::new(Origin::default(), 8).with_disk_store(&dir);
+        let signed = get().with_header("CB-ACCESS-SIGN", "sig");
+        client.execute(signed).await.unwrap();
+        assert!(files(&dir).is_empty());
+
+        let client = Cached::new(Origin::default(), 8)
+            .with_store(DiskStore::new(&dir).with_authenticated_responses(true));
+        let signed = get().with_header("Authorization", "Bearer t");
+        client.execute(signed).await.unwrap();
+        let names = files(&dir);
+        assert_eq!(names.len(), 1);
+        assert!(names[0].ends_with(".json") && !names[0].contains("assets"));
+        let stored = std::fs::read_to_string(dir.join(&names[0])).unwrap();
+        assert!(!stored.contains("session=abc") && !stored.contains("Bearer"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn disk_entries_survive_a_new_client() {
+        let dir = temp_dir();
+        let client = Cached::new(Origin::default(), 8).with_disk_store(&dir);
+        client.execute(get()).await.unwrap();
+
+        let restarted = Cached::new(Origin::default(), 8).with_disk_store(&dir);
+        let response = restarted.execute(get()).await.unwrap();
+        assert_eq!(response.cache_status(), Some(CacheStatus::Hit));
+        assert_eq!(restarted.inner().calls.load(Ordering::SeqCst), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
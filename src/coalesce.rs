@@ -0,0 +1,312 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_client::HttpClient;
+use crate::http_method::HttpMethod;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use async_trait::async_trait;
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+type SharedResult = Result<HttpResponse, Arc<HttpError>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RequestKey {
+    method: String,
+    url: String,
+    path: Option<String>,
+    query: Vec<(String, String)>,
+    vary: Vec<Option<String>>,
+}
+
+struct InFlight {
+    id: u64,
+    future: Shared<BoxFuture<'static, SharedResult>>,
+}
+
+/// Wraps a client so that concurrent identical GETs share a single request.
+///
+/// Requests match when their method, URL, path, query parameters and the values of the
+/// configured vary headers are equal. Every caller gets its own clone of the buffered
+/// response, carrying only the extensions of that caller's request; errors are shared as
+/// [`HttpError::Shared`]. Headers added later by the inner
+/// client, such as auth signatures, are not part of the key, so list any caller-supplied
+/// header that changes the response with [`Coalesced::with_vary_header`].
+pub struct Coalesced<C> {
+    inner: Arc<C>,
+    vary_headers: Vec<String>,
+    in_flight: Mutex<HashMap<RequestKey, InFlight>>,
+    next_id: AtomicU64,
+}
+
+impl<C: HttpClient + 'static> Coalesced<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            vary_headers: Vec::new(),
+            in_flight: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_vary_header(mut self, name: impl Into<String>) -> Self {
+        self.vary_headers.push(name.into());
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn key(&self, request: &HttpRequest) -> RequestKey {
+        let mut query: Vec<(String, String)> = request
+            .query_params
+            .iter()
+            .flatten()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        query.sort();
+        RequestKey {
+            method: request.method.to_string(),
+            url: request
+                .url
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            path: request.path.clone(),
+            query,
+            vary: self
+                .vary_headers
+                .iter()
+                .map(|name| request.headers.get(name).map(str::to_string))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient + 'static> HttpClient for Coalesced<C> {
+    async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+        if request.method != HttpMethod::Get {
+            return self.inner.execute(request).await;
+        }
+
+        let key = self.key(&request);
+        let extensions = request.extensions().clone();
+        let (id, future) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(existing) => (existing.id, existing.future.clone()),
+                None => {
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    let inner = self.inner.clone();
+                    let future = async move {
+                        match inner.execute(request).await {
+                            Ok(response) => response.buffer().await.map_err(Arc::new),
+                            Err(e) => Err(Arc::new(e)),
+                        }
+                    }
+                    .boxed()
+                    .shared();
+                    in_flight.insert(
+                        key.clone(),
+                        InFlight {
+                            id,
+                            future: future.clone(),
+                        },
+                    );
+                    (id, future)
+                }
+            }
+        };
+
+        let result = future.await;
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.get(&key).is_some_and(|entry| entry.id == id) {
+                in_flight.remove(&key);
+            }
+        }
+        // Each caller sees only its own request's extensions on the shared response, never
+        // values the leading request carried
+        result
+            .map_err(HttpError::Shared)
+            .map(|response| response.with_extensions(extensions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_status_code::HttpStatusCode;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    // Answers after 50ms, echoing the `x-account` header, or fails for `/down`. Like the
+    // real client it copies the request's extensions onto the response.
+    struct Server {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl HttpClient for Server {
+        async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if request.path.as_deref() == Some("/down") {
+                return Err(HttpError::Custom("connection reset".to_string()));
+            }
+            let account = request
+                .headers
+                .get("x-account")
+                .unwrap_or("none")
+                .to_string();
+            Ok(HttpResponse::new(HttpStatusCode::Ok)
+                .with_header("x-account", account)
+                .with_extensions(request.extensions().clone()))
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Caller(u32);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct LeaderOnly;
+
+    fn coalesced() -> Arc<Coalesced<Server>> {
+        Arc::new(
+            Coalesced::new(Server {
+                calls: AtomicUsize::new(0),
+            })
+            .with_vary_header("x-account"),
+        )
+    }
+
+    fn get(path: &str) -> HttpRequest {
+        HttpRequest::new(HttpMethod::Get, path).unwrap()
+    }
+
+    async fn concurrently(
+        client: &Arc<Coalesced<Server>>,
+        requests: Vec<HttpRequest>,
+    ) -> Vec<HttpResult<HttpResponse>> {
+        let tasks: Vec<_> = requests
+            .into_iter()
+            .map(|request| {
+                let client = client.clone();
+                tokio::spawn(async move { client.execute(request).await })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for task in tasks {
+            results.push(task.await.unwrap());
+        }
+        results
+    }
+
+    fn calls(client: &Coalesced<Server>) -> usize {
+        client.inner().calls.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_gets_share_one_call() {
+        let client = coalesced();
+        let results = concurrently(&client, (0..5).map(|_| get("/orders")).collect()).await;
+        assert_eq!(calls(&client), 1);
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        // once settled, the next request goes out again
+        client.execute(get("/orders")).await.unwrap();
+        assert_eq!(calls(&client), 2);
+    }
+
+    #[tokio::test]
+    async fn vary_headers_and_queries_keep_requests_apart() {
+        let client = coalesced();
+        let mut query = HashMap::new();
+        query.insert("limit".to_string(), "5".to_string());
+        let results = concurrently(
+            &client,
+            vec![
+                get("/orders").with_header("x-account", "a"),
+                get("/orders").with_header("x-account", "b"),
+                get("/orders").with_header("x-account", "a"),
+                get("/orders")
+                    .with_header("x-account", "a")
+                    .with_query_params(query),
+            ],
+        )
+        .await;
+        assert_eq!(calls(&client), 3);
+        let accounts: Vec<_> = results
+            .iter()
+            .map(|result| result.as_ref().unwrap().header("x-account").unwrap())
+            .collect();
+        assert_eq!(accounts, ["a", "b", "a", "a"]);
+    }
+
+    #[tokio::test]
+    async fn errors_reach_every_waiter() {
+        let client = coalesced();
+        let results = concurrently(&client, (0..3).map(|_| get("/down")).collect()).await;
+        assert_eq!(calls(&client), 1);
+        for result in results {
+            match result {
+                Err(HttpError::Shared(error)) => {
+                    assert!(error.to_string().contains("connection reset"))
+                }
+                other => panic!("expected a shared error, got {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn other_methods_pass_through() {
+        let client = coalesced();
+        let posts = (0..3)
+            .map(|_| HttpRequest::new(HttpMethod::Post, "/orders").unwrap())
+            .collect();
+        concurrently(&client, posts).await;
+        assert_eq!(calls(&client), 3);
+    }
+
+    #[tokio::test]
+    async fn each_caller_gets_only_its_own_extensions() {
+        let client = coalesced();
+        let leader = client.clone();
+        let leading = tokio::spawn(async move {
+            let request = get("/orders")
+                .with_extension(Caller(1))
+                .with_extension(LeaderOnly);
+            leader.execute(request).await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let follower = client
+            .execute(get("/orders").with_extension(Caller(2)))
+            .await
+            .unwrap();
+        let leader = leading.await.unwrap().unwrap();
+        assert_eq!(calls(&client), 1);
+
+        assert_eq!(leader.extensions().get::<Caller>(), Some(&Caller(1)));
+        assert_eq!(leader.extensions().get::<LeaderOnly>(), Some(&LeaderOnly));
+        assert_eq!(follower.extensions().get::<Caller>(), Some(&Caller(2)));
+        assert_eq!(follower.extensions().get::<LeaderOnly>(), None);
+    }
+}
@@ -0,0 +1,253 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_headers::HttpHeaders;
+use crate::http_request::HttpRequest;
+use std::fmt;
+use std::sync::Arc;
+
+pub const CORRELATION_ID_HEADER: &str = "X-Correlation-Id";
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Which header carries our correlation id on outgoing requests, and which response
+/// headers carry the server's request id.
+///
+/// A correlation id the caller already put on the request is kept; otherwise one is
+/// generated once per request and sent unchanged on every retry.
+#[derive(Clone)]
+pub struct Correlation {
+    header: String,
+    generator: Arc<dyn Fn() -> String + Send + Sync>,
+    request_id_headers: Vec<String>,
+}
+
+impl Default for Correlation {
+    fn default() -> Self {
+        Self {
+            header: CORRELATION_ID_HEADER.to_string(),
+            generator: Arc::new(generate_id),
+            request_id_headers: vec![REQUEST_ID_HEADER.to_string()],
+        }
+    }
+}
+
+impl fmt::Debug for Correlation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Correlation")
+            .field("header", &self.header)
+            .field("request_id_headers", &self.request_id_headers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Correlation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send the correlation id in this header instead of `X-Correlation-Id`
+    pub fn with_header(mut self, name: impl Into<String>) -> Self {
+        self.header = name.into();
+        self
+    }
+
+    /// Produce correlation ids with this function instead of random UUIDs
+    pub fn with_generator(
+        mut self,
+        generator: impl Fn() -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.generator = Arc::new(generator);
+        self
+    }
+
+    /// Also look for the server's request id in this response header, after the ones
+    /// already listed
+    pub fn with_request_id_header(mut self, name: impl Into<String>) -> Self {
+        self.request_id_headers.push(name.into());
+        self
+    }
+
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// The server's request id, from the first configured header present in `headers`
+    pub fn request_id(&self, headers: &HttpHeaders) -> Option<String> {
+        request_id(headers, &self.request_id_headers)
+    }
+
+    /// Attach the request's correlation id once, before the first attempt, and return it
+    pub(crate) fn apply(&self, request: &mut HttpRequest) -> HttpResult<String> {
+        if let Some(existing) = request.headers.get(&self.header) {
+            return Ok(existing.to_string());
+        }
+        let id = (self.generator)();
+        request
+            .headers
+            .insert(self.header.clone(), id.clone())
+            .map_err(|e| HttpError::Config(format!("Invalid correlation header: {e}")))?;
+        Ok(id)
+    }
+}
+
+/// A new random (v4) UUID correlation id
+pub fn generate_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The first of `names` present in `headers`
+pub(crate) fn request_id<S: AsRef<str>>(headers: &HttpHeaders, names: &[S]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| headers.get(name.as_ref()))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::Endpoint;
+    use crate::http_client::{HttpClient, ReqwestClient};
+    use crate::http_method::HttpMethod;
+    use crate::http_request::RetryPolicy;
+    use crate::http_response::HttpResponse;
+    use crate::http_status_code::HttpStatusCode;
+    use crate::http_url::HttpUrl;
+    use crate::transport::Transport;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    // The correlation id and URL of each attempt
+    type Seen = Arc<Mutex<Vec<(Option<String>, String)>>>;
+
+    // Answers each attempt with the next scripted outcome and records what it was sent with
+    struct Scripted {
+        outcomes: Mutex<Vec<HttpResult<HttpResponse>>>,
+        seen: Seen,
+    }
+
+    #[async_trait]
+    impl Transport for Scripted {
+        async fn send(&self, request: &HttpRequest, url: HttpUrl) -> HttpResult<HttpResponse> {
+            self.seen.lock().unwrap().push((
+                request
+                    .headers
+                    .get(CORRELATION_ID_HEADER)
+                    .map(str::to_string),
+                url.to_string(),
+            ));
+            self.outcomes.lock().unwrap().remove(0)
+        }
+    }
+
+    fn client(
+        outcomes: Vec<HttpResult<HttpResponse>>,
+        correlation: Correlation,
+    ) -> (ReqwestClient, Seen) {
+        let seen = Seen::default();
+        let client = ReqwestClient::builder()
+            .endpoints(vec![
+                Endpoint::new("https://a.example.com").unwrap(),
+                Endpoint::new("https://b.example.com")
+                    .unwrap()
+                    .with_priority(1),
+            ])
+            .default_retry_policy(RetryPolicy {
+                max_attempts: 3,
+                backoff_millis: 0,
+            })
+            .correlation(correlation)
+            .transport(Scripted {
+                outcomes: Mutex::new(outcomes),
+                seen: seen.clone(),
+            })
+            .build()
+            .unwrap();
+        (client, seen)
+    }
+
+    fn counting_generator(calls: &Arc<AtomicUsize>) -> impl Fn() -> String + Send + Sync {
+        let calls = calls.clone();
+        move || format!("corr-{}", calls.fetch_add(1, Ordering::SeqCst))
+    }
+
+    fn get() -> HttpRequest {
+        HttpRequest::new(HttpMethod::Get, "/orders").unwrap()
+    }
+
+    #[test]
+    fn apply_injects_once_and_keeps_an_existing_id() {
+        let correlation = Correlation::new()
+            .with_header("X-Trace")
+            .with_generator(|| "generated".to_string());
+        let mut request = get();
+        assert_eq!(correlation.apply(&mut request).unwrap(), "generated");
+        assert_eq!(request.headers.get("x-trace"), Some("generated"));
+
+        let mut request = get().with_header("X-Trace", "mine");
+        assert_eq!(correlation.apply(&mut request).unwrap(), "mine");
+        assert_eq!(request.headers.get_all("x-trace").count(), 1);
+    }
+
+    #[test]
+    fn request_id_comes_from_the_first_configured_header_present() {
+        let correlation = Correlation::new().with_request_id_header("x-amzn-requestid");
+        let mut headers = HttpHeaders::new();
+        headers.insert("x-amzn-requestid", "aws-1").unwrap();
+        assert_eq!(correlation.request_id(&headers).as_deref(), Some("aws-1"));
+        headers.insert("x-request-id", "cb-1").unwrap();
+        assert_eq!(correlation.request_id(&headers).as_deref(), Some("cb-1"));
+        assert_eq!(correlation.request_id(&HttpHeaders::new()), None);
+    }
+
+    #[tokio::test]
+    async fn one_id_is_sent_on_every_retry_and_failover() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let outcomes = vec![
+            Err(HttpError::Custom("connection reset".to_string())),
+            Ok(HttpResponse::new(HttpStatusCode::ServiceUnavailable)),
+            Ok(HttpResponse::new(HttpStatusCode::Ok).with_header("x-request-id", "srv-1")),
+        ];
+        let correlation = Correlation::new().with_generator(counting_generator(&calls));
+        let (client, seen) = client(outcomes, correlation);
+
+        let response = client.execute(get()).await.unwrap();
+        assert_eq!(response.request_id(), Some("srv-1"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let seen = seen.lock().unwrap().clone();
+        assert_eq!(seen.len(), 3);
+        assert!(seen.iter().all(|(id, _)| id.as_deref() == Some("corr-0")));
+        // the attempts moved between endpoints
+        assert!(seen.iter().any(|(_, url)| url.starts_with("https://a.")));
+        assert!(seen.iter().any(|(_, url)| url.starts_with("https://b.")));
+    }
+
+    #[tokio::test]
+    async fn caller_ids_are_kept_and_status_errors_carry_the_request_id() {
+        let outcomes =
+            vec![Ok(HttpResponse::new(HttpStatusCode::NotFound)
+                .with_header("x-amzn-requestid", "srv-404"))];
+        let correlation = Correlation::new().with_request_id_header("x-amzn-requestid");
+        let (client, seen) = client(outcomes, correlation);
+
+        let request = get().with_header(CORRELATION_ID_HEADER, "caller-1");
+        let error = client.execute(request).await.unwrap_err();
+        assert_eq!(error.status(), Some(HttpStatusCode::NotFound));
+        assert_eq!(error.request_id(), Some("srv-404"));
+        assert_eq!(seen.lock().unwrap()[0].0.as_deref(), Some("caller-1"));
+    }
+}
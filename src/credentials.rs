@@ -15,10 +15,17 @@
  */
 use crate::http_method::HttpMethod;
 
+/// What a request signer covers: the method, the path as sent (with query) and the body.
+///
+/// Signers must check [`Credentials::unsigned_body`]: multipart uploads are streamed when
+/// sent, so their body is not available here and can only be signed by method and path.
+/// Refuse such requests if the API requires a body signature.
 pub struct Credentials {
     pub method: HttpMethod,
     pub path: String,
     pub body: Option<Vec<u8>>,
+    /// The request has a body that is not included in `body`
+    pub unsigned_body: bool,
 }
 
 impl Credentials {
@@ -27,6 +34,7 @@ impl Credentials {
             method,
             path: path.into(),
             body,
+            unsigned_body: false,
         }
     }
 }
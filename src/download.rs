@@ -0,0 +1,572 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_client::HttpClient;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::http_status_code::HttpStatusCode;
+use base64::Engine;
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+pub type ProgressCallback = Arc<dyn Fn(DownloadProgress) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct DownloadOptions {
+    progress: Option<ProgressCallback>,
+    expected_sha256: Option<String>,
+    max_resumes: u32,
+}
+
+impl DownloadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called after every chunk written to disk
+    pub fn with_progress(mut self, progress: ProgressCallback) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Hex-encoded SHA-256 the finished file must match. When unset, a `Repr-Digest` or
+    /// `Digest` response header is used if the server sends one.
+    pub fn with_expected_sha256(mut self, hex_digest: impl Into<String>) -> Self {
+        self.expected_sha256 = Some(hex_digest.into());
+        self
+    }
+
+    /// Number of times a dropped connection is resumed with a `Range` request
+    pub fn with_max_resumes(mut self, max_resumes: u32) -> Self {
+        self.max_resumes = max_resumes;
+        self
+    }
+}
+
+/// Download a response body to `path`, resuming with `Range` requests if the connection drops.
+///
+/// `make_request` is called for the initial request and again for every resume attempt.
+/// The body is written to a temporary file next to `path` and only renamed into place once
+/// it is complete and its checksum (if known) has been verified.
+pub async fn download<C, F>(
+    client: &C,
+    make_request: F,
+    path: impl AsRef<Path>,
+    options: DownloadOptions,
+) -> HttpResult<u64>
+where
+    C: HttpClient + ?Sized,
+    F: Fn() -> HttpResult<HttpRequest>,
+{
+    let response = client
+        .execute(make_request()?.with_streaming_response(true))
+        .await?;
+    let mut sink = DownloadSink::create(path.as_ref(), &options, &response).await?;
+    match resume_loop(
+        client,
+        &make_request,
+        &mut sink,
+        response,
+        options.max_resumes,
+    )
+    .await
+    {
+        Ok(()) => sink.finish().await,
+        Err(e) => {
+            sink.abort().await;
+            Err(e)
+        }
+    }
+}
+
+async fn resume_loop<C, F>(
+    client: &C,
+    make_request: &F,
+    sink: &mut DownloadSink,
+    mut response: HttpResponse,
+    max_resumes: u32,
+) -> HttpResult<()>
+where
+    C: HttpClient + ?Sized,
+    F: Fn() -> HttpResult<HttpRequest>,
+{
+    let mut resumes = 0;
+    loop {
+        match sink.consume(response).await {
+            Ok(()) => return Ok(()),
+            Err(SinkError::Transport(_)) if resumes < max_resumes => {
+                resumes += 1;
+                let mut request = make_request()?.with_streaming_response(true);
+                request
+                    .add_header("Range", &format!("bytes={}-", sink.written))
+                    .map_err(|e| HttpError::Custom(e.to_string()))?;
+                if let Some(validator) = &sink.validator {
+                    request
+                        .add_header("If-Range", validator)
+                        .map_err(|e| HttpError::Custom(e.to_string()))?;
+                }
+                response = client.execute(request).await?;
+                sink.prepare_resume(&response).await?;
+            }
+            Err(SinkError::Transport(e)) | Err(SinkError::Fatal(e)) => return Err(e),
+        }
+    }
+}
+
+pub(crate) async fn download_response(
+    response: HttpResponse,
+    path: &Path,
+    options: &DownloadOptions,
+) -> HttpResult<u64> {
+    let mut sink = DownloadSink::create(path, options, &response).await?;
+    match sink.consume(response).await {
+        Ok(()) => sink.finish().await,
+        Err(SinkError::Transport(e)) | Err(SinkError::Fatal(e)) => {
+            sink.abort().await;
+            Err(e)
+        }
+    }
+}
+
+enum SinkError {
+    // The connection dropped mid-body; the download can be resumed
+    Transport(HttpError),
+    Fatal(HttpError),
+}
+
+struct DownloadSink {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    file: File,
+    hasher: Sha256,
+    written: u64,
+    total: Option<u64>,
+    expected: Option<Vec<u8>>,
+    // Whether `expected` came from the caller rather than a response header
+    expected_pinned: bool,
+    validator: Option<String>,
+    progress: Option<ProgressCallback>,
+}
+
+impl DownloadSink {
+    async fn create(
+        path: &Path,
+        options: &DownloadOptions,
+        response: &HttpResponse,
+    ) -> HttpResult<Self> {
+        let expected = match &options.expected_sha256 {
+            Some(hex_digest) => Some(
+                hex::decode(hex_digest.trim())
+                    .map_err(|e| HttpError::Custom(format!("Invalid expected SHA-256: {e}")))?,
+            ),
+            None => header_sha256(response),
+        };
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| HttpError::Custom(format!("Invalid download path: {}", path.display())))?
+            .to_string_lossy();
+        let tmp_path = path.with_file_name(format!(".{}.part", file_name));
+        let file = File::create(&tmp_path).await?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            tmp_path,
+            file,
+            hasher: Sha256::new(),
+            written: 0,
+            total: response.content_length(),
+            expected,
+            expected_pinned: options.expected_sha256.is_some(),
+            validator: validator(response),
+            progress: options.progress.clone(),
+        })
+    }
+
+    async fn consume(&mut self, response: HttpResponse) -> Result<(), SinkError> {
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(SinkError::Transport)?;
+            self.file
+                .write_all(&chunk)
+                .await
+                .map_err(|e| SinkError::Fatal(e.into()))?;
+            self.hasher.update(&chunk);
+            self.written += chunk.len() as u64;
+            if let Some(progress) = &self.progress {
+                progress(DownloadProgress {
+                    downloaded: self.written,
+                    total: self.total,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn prepare_resume(&mut self, response: &HttpResponse) -> HttpResult<()> {
+        match response.status() {
+            HttpStatusCode::PartialContent => {
+                let range = response
+                    .header("content-range")
+                    .and_then(parse_content_range);
+                match range {
+                    Some((start, total)) if start == self.written => {
+                        if total.is_some() {
+                            self.total = total;
+                        }
+                        Ok(())
+                    }
+                    _ => Err(HttpError::Custom(format!(
+                        "Server resumed download at an unexpected offset (expected {})",
+                        self.written
+                    ))),
+                }
+            }
+            // The server ignored the range or the resource changed; start over, describing
+            // the representation this response carries
+            _ => {
+                self.file = File::create(&self.tmp_path).await?;
+                self.hasher = Sha256::new();
+                self.written = 0;
+                self.total = response.content_length();
+                if !self.expected_pinned {
+                    self.expected = header_sha256(response);
+                }
+                self.validator = validator(response);
+                Ok(())
+            }
+        }
+    }
+
+    async fn finish(mut self) -> HttpResult<u64> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+        let actual = self.hasher.finalize_reset().to_vec();
+        if let Some(expected) = &self.expected {
+            if *expected != actual {
+                let _ = fs::remove_file(&self.tmp_path).await;
+                return Err(HttpError::ChecksumMismatch {
+                    expected: hex::encode(expected),
+                    actual: hex::encode(&actual),
+                });
+            }
+        }
+        if let Err(e) = fs::rename(&self.tmp_path, &self.path).await {
+            let _ = fs::remove_file(&self.tmp_path).await;
+            return Err(e.into());
+        }
+        Ok(self.written)
+    }
+
+    async fn abort(self) {
+        drop(self.file);
+        let _ = fs::remove_file(&self.tmp_path).await;
+    }
+}
+
+// The validator to send in `If-Range`: a strong ETag, or else Last-Modified. Weak ETags
+// can't be used there (RFC 9110 §13.1.5).
+fn validator(response: &HttpResponse) -> Option<String> {
+    response
+        .header("etag")
+        .filter(|etag| !etag.trim_start().starts_with("W/"))
+        .or_else(|| response.header("last-modified"))
+        .map(str::to_string)
+}
+
+// Parse `bytes <start>-<end>/<total>` into the start offset and the total length, if known
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let (start, _) = span.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+// Read a SHA-256 of the full representation from `Repr-Digest` (RFC 9530) or `Digest` (RFC 3230)
+fn header_sha256(response: &HttpResponse) -> Option<Vec<u8>> {
+    let engine = base64::engine::general_purpose::STANDARD;
+
+    if let Some(value) = response.header("repr-digest") {
+        for entry in value.split(',') {
+            if let Some((alg, digest)) = entry.trim().split_once('=') {
+                if alg.eq_ignore_ascii_case("sha-256") {
+                    return engine.decode(digest.trim().trim_matches(':')).ok();
+                }
+            }
+        }
+    }
+    if let Some(value) = response.header("digest") {
+        for entry in value.split(',') {
+            if let Some((alg, digest)) = entry.trim().split_once('=') {
+                if alg.eq_ignore_ascii_case("sha-256") {
+                    return engine.decode(digest.trim()).ok();
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_headers::HttpHeaders;
+    use crate::http_method::HttpMethod;
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use futures_util::stream;
+    use std::sync::Mutex;
+
+    const BODY: &[u8] = b"0123456789abcdef";
+    const CHANGED: &[u8] = b"fedcba9876543210";
+
+    fn sha256_hex(bytes: &[u8]) -> String {
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("core-rs-{}-{name}", uuid::Uuid::new_v4()))
+    }
+
+    // Serves BODY, dropping the first connection after `cut` bytes, and records the
+    // Range/If-Range headers of each request. With `changed`, later requests get a new
+    // version of the resource (CHANGED, a new ETag and its digest) in full.
+    struct FlakyServer {
+        cut: usize,
+        honour_range: bool,
+        changed: bool,
+        etag: &'static str,
+        seen: Mutex<Vec<(Option<String>, Option<String>)>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for FlakyServer {
+        async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+            let range = request.headers.get("range").map(str::to_string);
+            let if_range = request.headers.get("if-range").map(str::to_string);
+            let first = {
+                let mut seen = self.seen.lock().unwrap();
+                seen.push((range.clone(), if_range));
+                seen.len() == 1
+            };
+            let mut headers = HttpHeaders::new();
+            if self.changed && !first {
+                headers.insert("etag", "\"v2\"").unwrap();
+                headers.insert("repr-digest", repr_digest(CHANGED)).unwrap();
+                return Ok(HttpResponse::from_parts(
+                    HttpStatusCode::Ok,
+                    headers,
+                    Bytes::from_static(CHANGED),
+                ));
+            }
+            headers.insert("etag", self.etag).unwrap();
+            headers
+                .insert("last-modified", "Tue, 15 Oct 2024 07:28:00 GMT")
+                .unwrap();
+            if first {
+                headers
+                    .insert("content-length", BODY.len().to_string())
+                    .unwrap();
+                headers.insert("repr-digest", repr_digest(BODY)).unwrap();
+                let chunks: Vec<HttpResult<Bytes>> = vec![
+                    Ok(Bytes::from_static(&BODY[..self.cut])),
+                    Err(HttpError::Custom("connection reset".into())),
+                ];
+                return Ok(HttpResponse::streaming(
+                    HttpStatusCode::Ok,
+                    headers,
+                    stream::iter(chunks),
+                ));
+            }
+            let start: usize = range
+                .as_deref()
+                .and_then(|r| r.strip_prefix("bytes="))
+                .and_then(|r| r.trim_end_matches('-').parse().ok())
+                .unwrap();
+            if !self.honour_range {
+                return Ok(HttpResponse::from_parts(
+                    HttpStatusCode::Ok,
+                    headers,
+                    Bytes::from_static(BODY),
+                ));
+            }
+            headers
+                .insert(
+                    "content-range",
+                    format!("bytes {start}-{}/{}", BODY.len() - 1, BODY.len()),
+                )
+                .unwrap();
+            Ok(HttpResponse::from_parts(
+                HttpStatusCode::PartialContent,
+                headers,
+                Bytes::from_static(&BODY[start..]),
+            ))
+        }
+    }
+
+    fn repr_digest(body: &[u8]) -> String {
+        let b64 = base64::engine::general_purpose::STANDARD.encode(Sha256::digest(body));
+        format!("sha-256=:{b64}:")
+    }
+
+    fn server(honour_range: bool) -> FlakyServer {
+        FlakyServer {
+            cut: 5,
+            honour_range,
+            changed: false,
+            etag: "\"v1\"",
+            seen: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn request() -> HttpResult<HttpRequest> {
+        Ok(HttpRequest::new(HttpMethod::Get, "https://example.com/file").unwrap())
+    }
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(parse_content_range("bytes 5-15/16"), Some((5, Some(16))));
+        assert_eq!(parse_content_range(" bytes 0-9/* "), Some((0, None)));
+        assert_eq!(parse_content_range("items 0-9/10"), None);
+        assert_eq!(parse_content_range("bytes x-9/10"), None);
+    }
+
+    #[test]
+    fn reads_sha256_from_digest_headers() {
+        let digest = Sha256::digest(BODY).to_vec();
+        let b64 = base64::engine::general_purpose::STANDARD.encode(&digest);
+
+        let response = HttpResponse::new(HttpStatusCode::Ok)
+            .with_header("repr-digest", format!("sha-512=:AAAA:, sha-256=:{b64}:"));
+        assert_eq!(header_sha256(&response), Some(digest.clone()));
+
+        let response = HttpResponse::new(HttpStatusCode::Ok)
+            .with_header("digest", format!("MD5=abc, SHA-256={b64}"));
+        assert_eq!(header_sha256(&response), Some(digest));
+
+        let response = HttpResponse::new(HttpStatusCode::Ok).with_header("digest", "md5=abc");
+        assert_eq!(header_sha256(&response), None);
+    }
+
+    #[tokio::test]
+    async fn resumes_with_range_and_if_range() {
+        let server = server(true);
+        let path = temp_path("resume");
+        let options = DownloadOptions::new()
+            .with_max_resumes(1)
+            .with_expected_sha256(sha256_hex(BODY));
+        let written = download(&server, request, &path, options).await.unwrap();
+        assert_eq!(written, BODY.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        let seen = server.seen.lock().unwrap().clone();
+        assert_eq!(
+            seen,
+            vec![
+                (None, None),
+                (Some("bytes=5-".into()), Some("\"v1\"".into()))
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn restarts_when_the_server_ignores_the_range() {
+        let server = server(false);
+        let path = temp_path("restart");
+        let options = DownloadOptions::new().with_max_resumes(1);
+        download(&server, request, &path, options).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn restart_takes_digest_and_validator_from_the_new_response() {
+        let changed = FlakyServer {
+            changed: true,
+            ..server(true)
+        };
+        let path = temp_path("changed");
+        let options = DownloadOptions::new().with_max_resumes(2);
+        download(&changed, request, &path, options).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), CHANGED);
+        std::fs::remove_file(path).unwrap();
+
+        // A digest the caller pinned still applies to the restarted body
+        let changed = FlakyServer {
+            changed: true,
+            ..server(true)
+        };
+        let path = temp_path("changed-pinned");
+        let options = DownloadOptions::new()
+            .with_max_resumes(2)
+            .with_expected_sha256(sha256_hex(BODY));
+        let result = download(&changed, request, &path, options).await;
+        assert!(matches!(result, Err(HttpError::ChecksumMismatch { .. })));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn weak_etags_are_not_sent_in_if_range() {
+        let server = FlakyServer {
+            etag: "W/\"v1\"",
+            ..server(true)
+        };
+        let path = temp_path("weak");
+        let options = DownloadOptions::new().with_max_resumes(1);
+        download(&server, request, &path, options).await.unwrap();
+        let seen = server.seen.lock().unwrap().clone();
+        assert_eq!(seen[1].1.as_deref(), Some("Tue, 15 Oct 2024 07:28:00 GMT"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gives_up_without_resumes() {
+        let server = server(true);
+        let path = temp_path("noresume");
+        let result = download(&server, request, &path, DownloadOptions::new()).await;
+        assert!(result.is_err());
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_leaves_no_file() {
+        let path = temp_path("mismatch");
+        let response = HttpResponse::from_parts(
+            HttpStatusCode::Ok,
+            HttpHeaders::new(),
+            Bytes::from_static(BODY),
+        );
+        let options = DownloadOptions::new().with_expected_sha256(sha256_hex(b"other"));
+        let result = response.download_to_with(&path, &options).await;
+        assert!(matches!(result, Err(HttpError::ChecksumMismatch { .. })));
+        assert!(!path.exists());
+        let tmp = path.with_file_name(format!(
+            ".{}.part",
+            path.file_name().unwrap().to_string_lossy()
+        ));
+        assert!(!tmp.exists());
+    }
+}
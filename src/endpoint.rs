@@ -0,0 +1,310 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_url::HttpUrl;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+const MAX_COOLDOWN_MULTIPLIER: u32 = 8;
+
+/// A base URL that requests can be sent to
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub url: HttpUrl,
+    /// Lower values are preferred; endpoints with a higher value are only used for failover
+    pub priority: u32,
+    /// Relative share of traffic among healthy endpoints of the same priority
+    pub weight: u32,
+}
+
+impl Endpoint {
+    pub fn new(url: &str) -> HttpResult<Self> {
+        let url = HttpUrl::parse(url)
+            .map_err(|e| HttpError::Config(format!("Invalid endpoint URL '{url}': {e}")))?;
+        Ok(Self {
+            url,
+            priority: 0,
+            weight: 1,
+        })
+    }
+
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight.max(1);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct EndpointState {
+    endpoint: Endpoint,
+    health: Mutex<Health>,
+}
+
+/// A set of endpoints with passive health tracking.
+///
+/// Transport errors, timeouts and 5xx responses mark an endpoint unhealthy for a cooldown
+/// that grows with consecutive failures; any other response marks it healthy again.
+#[derive(Debug)]
+pub struct EndpointSet {
+    endpoints: Vec<EndpointState>,
+    cooldown: Duration,
+    counter: AtomicU64,
+}
+
+impl EndpointSet {
+    pub fn new(endpoints: Vec<Endpoint>) -> HttpResult<Self> {
+        if endpoints.is_empty() {
+            return Err(HttpError::Config(
+                "at least one endpoint is required".to_string(),
+            ));
+        }
+        Ok(Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|endpoint| EndpointState {
+                    endpoint,
+                    health: Mutex::new(Health::default()),
+                })
+                .collect(),
+            cooldown: DEFAULT_COOLDOWN,
+            counter: AtomicU64::new(0),
+        })
+    }
+
+    /// How long an endpoint is avoided after its first failure
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn endpoint(&self, index: usize) -> &Endpoint {
+        &self.endpoints[index].endpoint
+    }
+
+    pub fn is_healthy(&self, index: usize) -> bool {
+        let health = self.endpoints[index].health.lock().unwrap();
+        health
+            .unhealthy_until
+            .is_none_or(|until| Instant::now() >= until)
+    }
+
+    /// Pick the endpoint for the next attempt, skipping ones already tried for this request
+    /// while untried ones remain.
+    ///
+    /// Healthy endpoints with the lowest priority are chosen by weight. If every candidate
+    /// is unhealthy, the one that recovers soonest is used.
+    pub fn select(&self, tried: &[usize]) -> usize {
+        let mut candidates: Vec<usize> = (0..self.endpoints.len())
+            .filter(|i| !tried.contains(i))
+            .collect();
+        if candidates.is_empty() {
+            candidates = (0..self.endpoints.len()).collect();
+        }
+
+        let healthy: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|&i| self.is_healthy(i))
+            .collect();
+        if healthy.is_empty() {
+            return candidates
+                .into_iter()
+                .min_by_key(|&i| self.endpoints[i].health.lock().unwrap().unhealthy_until)
+                .unwrap_or(0);
+        }
+
+        let best = healthy
+            .iter()
+            .map(|&i| self.endpoints[i].endpoint.priority)
+            .min()
+            .unwrap_or_default();
+        let group: Vec<usize> = healthy
+            .into_iter()
+            .filter(|&i| self.endpoints[i].endpoint.priority == best)
+            .collect();
+        let total: u64 = group
+            .iter()
+            .map(|&i| u64::from(self.endpoints[i].endpoint.weight))
+            .sum();
+        let mut ticket = self.counter.fetch_add(1, Ordering::Relaxed) % total.max(1);
+        for &i in &group {
+            let weight = u64::from(self.endpoints[i].endpoint.weight);
+            if ticket < weight {
+                return i;
+            }
+            ticket -= weight;
+        }
+        group[0]
+    }
+
+    pub fn mark_success(&self, index: usize) {
+        let mut health = self.endpoints[index].health.lock().unwrap();
+        *health = Health::default();
+    }
+
+    pub fn mark_failure(&self, index: usize) {
+        let mut health = self.endpoints[index].health.lock().unwrap();
+        health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+        let multiplier = 2u32
+            .saturating_pow(health.consecutive_failures - 1)
+            .min(MAX_COOLDOWN_MULTIPLIER);
+        health.unhealthy_until = Some(Instant::now() + self.cooldown * multiplier);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(endpoints: &[(&str, u32, u32)]) -> EndpointSet {
+        EndpointSet::new(
+            endpoints
+                .iter()
+                .map(|&(url, priority, weight)| {
+                    Endpoint::new(url)
+                        .unwrap()
+                        .with_priority(priority)
+                        .with_weight(weight)
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    // The cooldown the latest failure set, measured from just before it
+    fn cooldown_after_failure(set: &EndpointSet, index: usize) -> Duration {
+        let before = Instant::now();
+        set.mark_failure(index);
+        let until = set.endpoints[index].health.lock().unwrap().unhealthy_until;
+        until.unwrap().duration_since(before)
+    }
+
+    #[test]
+    fn lowest_priority_healthy_endpoint_is_preferred() {
+        let set = set(&[
+            ("https://backup.example.com", 1, 1),
+            ("https://primary.example.com", 0, 1),
+        ]);
+        for _ in 0..4 {
+            assert_eq!(set.select(&[]), 1);
+        }
+        // retries move to an untried endpoint, then back once all have been tried
+        assert_eq!(set.select(&[1]), 0);
+        assert_eq!(set.select(&[0, 1]), 1);
+
+        set.mark_failure(1);
+        assert_eq!(set.select(&[]), 0);
+    }
+
+    #[test]
+    fn weights_split_traffic_within_a_priority() {
+        let set = set(&[
+            ("https://a.example.com", 0, 3),
+            ("https://b.example.com", 0, 1),
+            ("https://c.example.com", 1, 100),
+        ]);
+        let mut counts = [0; 3];
+        for _ in 0..400 {
+            counts[set.select(&[])] += 1;
+        }
+        assert_eq!(counts, [300, 100, 0]);
+    }
+
+    #[test]
+    fn cooldown_doubles_per_failure_up_to_eight_times() {
+        let cooldown = Duration::from_secs(10);
+        let set = set(&[("https://a.example.com", 0, 1)]).with_cooldown(cooldown);
+        for multiplier in [1, 2, 4, 8, 8, 8] {
+            let actual = cooldown_after_failure(&set, 0);
+            let expected = cooldown * multiplier;
+            assert!(
+                actual >= expected && actual < expected + Duration::from_secs(1),
+                "expected about {expected:?}, got {actual:?}"
+            );
+        }
+        assert!(!set.is_healthy(0));
+    }
+
+    #[test]
+    fn success_restores_health_and_resets_the_cooldown() {
+        let cooldown = Duration::from_secs(10);
+        let set = set(&[
+            ("https://a.example.com", 0, 1),
+            ("https://b.example.com", 1, 1),
+        ])
+        .with_cooldown(cooldown);
+        set.mark_failure(0);
+        set.mark_failure(0);
+        assert!(!set.is_healthy(0));
+        assert_eq!(set.select(&[]), 1);
+
+        set.mark_success(0);
+        assert!(set.is_healthy(0));
+        assert_eq!(set.select(&[]), 0);
+        assert!(cooldown_after_failure(&set, 0) < cooldown * 2);
+    }
+
+    #[test]
+    fn expired_cooldowns_and_all_unhealthy_sets_still_select() {
+        let cooling = set(&[
+            ("https://a.example.com", 0, 1),
+            ("https://b.example.com", 0, 1),
+        ])
+        .with_cooldown(Duration::from_secs(60));
+        cooling.mark_failure(0);
+        cooling.mark_failure(0);
+        cooling.mark_failure(1);
+        // every endpoint is cooling down: the one that recovers soonest is used
+        assert_eq!(cooling.select(&[]), 1);
+
+        let expired = set(&[("https://a.example.com", 0, 1)]).with_cooldown(Duration::ZERO);
+        expired.mark_failure(0);
+        assert!(expired.is_healthy(0));
+    }
+
+    #[test]
+    fn empty_sets_and_bad_urls_are_config_errors() {
+        assert!(matches!(
+            EndpointSet::new(Vec::new()),
+            Err(HttpError::Config(_))
+        ));
+        assert!(matches!(
+            Endpoint::new("not a url"),
+            Err(HttpError::Config(_))
+        ));
+    }
+}
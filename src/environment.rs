@@ -0,0 +1,305 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const PRIME_PRODUCTION_REST_URL: &str = "https://api.prime.coinbase.com/v1";
+pub const PRIME_PRODUCTION_WEBSOCKET_URL: &str = "wss://ws-feed.prime.coinbase.com";
+pub const PRIME_PRODUCTION_FIX_ENDPOINT: &str = "fix.prime.coinbase.com:4198";
+
+/// Environment variable naming the profile to use when none is given explicitly
+pub const PROFILE_ENV_VAR: &str = "CORE_RS_PROFILE";
+
+/// The set of endpoints a client talks to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Environment {
+    pub name: String,
+    pub rest_base_url: String,
+    pub websocket_url: Option<String>,
+    pub fix_endpoint: Option<String>,
+}
+
+impl Environment {
+    pub fn new(name: impl Into<String>, rest_base_url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            rest_base_url: rest_base_url.into(),
+            websocket_url: None,
+            fix_endpoint: None,
+        }
+    }
+
+    pub fn prime_production() -> Self {
+        Self {
+            name: "production".to_string(),
+            rest_base_url: PRIME_PRODUCTION_REST_URL.to_string(),
+            websocket_url: Some(PRIME_PRODUCTION_WEBSOCKET_URL.to_string()),
+            fix_endpoint: Some(PRIME_PRODUCTION_FIX_ENDPOINT.to_string()),
+        }
+    }
+
+    pub fn with_websocket_url(mut self, url: impl Into<String>) -> Self {
+        self.websocket_url = Some(url.into());
+        self
+    }
+
+    pub fn with_fix_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.fix_endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Look up a built-in environment by name.
+    ///
+    /// Sandbox hosts are provisioned per account, so sandbox profiles set their URLs in the
+    /// profile config rather than relying on a preset.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "production" | "prod" | "prime-production" => Some(Self::prime_production()),
+            _ => None,
+        }
+    }
+}
+
+/// A resolved profile: the environment to talk to and the name of the credentials to use.
+///
+/// Credentials are referenced by name only so that secrets stay in the caller's secret
+/// store rather than in the profile file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub environment: Environment,
+    pub credentials: Option<String>,
+}
+
+impl Profile {
+    /// Build a profile from `<PREFIX>_ENVIRONMENT`, `<PREFIX>_REST_BASE_URL`,
+    /// `<PREFIX>_WEBSOCKET_URL`, `<PREFIX>_FIX_ENDPOINT` and `<PREFIX>_CREDENTIALS`
+    pub fn from_env(prefix: &str) -> HttpResult<Self> {
+        let var = |key: &str| std::env::var(format!("{prefix}_{key}")).ok();
+        let spec = ProfileSpec {
+            environment: var("ENVIRONMENT"),
+            rest_base_url: var("REST_BASE_URL"),
+            websocket_url: var("WEBSOCKET_URL"),
+            fix_endpoint: var("FIX_ENDPOINT"),
+            credentials: var("CREDENTIALS"),
+        };
+        spec.resolve(&prefix.to_ascii_lowercase())
+    }
+}
+
+/// One entry of a profile file. A profile either names a preset `environment`, sets the
+/// URLs itself, or both, in which case the URLs override the preset.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileSpec {
+    pub environment: Option<String>,
+    pub rest_base_url: Option<String>,
+    pub websocket_url: Option<String>,
+    pub fix_endpoint: Option<String>,
+    pub credentials: Option<String>,
+}
+
+impl ProfileSpec {
+    pub fn resolve(&self, name: &str) -> HttpResult<Profile> {
+        let preset = match &self.environment {
+            Some(env) => Some(Environment::preset(env).ok_or_else(|| {
+                HttpError::Config(format!("profile '{name}': unknown environment '{env}'"))
+            })?),
+            None => None,
+        };
+        let rest_base_url = self
+            .rest_base_url
+            .clone()
+            .or_else(|| preset.as_ref().map(|p| p.rest_base_url.clone()))
+            .ok_or_else(|| {
+                HttpError::Config(format!(
+                    "profile '{name}' needs either an environment or a rest_base_url"
+                ))
+            })?;
+
+        Ok(Profile {
+            name: name.to_string(),
+            environment: Environment {
+                name: self.environment.clone().unwrap_or_else(|| name.to_string()),
+                rest_base_url,
+                websocket_url: self
+                    .websocket_url
+                    .clone()
+                    .or_else(|| preset.as_ref().and_then(|p| p.websocket_url.clone())),
+                fix_endpoint: self
+                    .fix_endpoint
+                    .clone()
+                    .or_else(|| preset.as_ref().and_then(|p| p.fix_endpoint.clone())),
+            },
+            credentials: self.credentials.clone(),
+        })
+    }
+}
+
+/// A set of named profiles, typically loaded from a config file:
+///
+/// ```toml
+/// default_profile = "prod"
+///
+/// [profiles.prod]
+/// environment = "production"
+/// credentials = "prime-prod"
+///
+/// [profiles.sandbox]
+/// rest_base_url = "https://sandbox.example.com/v1"
+/// credentials = "prime-sandbox"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileSpec>,
+}
+
+impl ProfileConfig {
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(contents: &str) -> HttpResult<Self> {
+        toml::from_str(contents).map_err(|e| HttpError::Config(format!("invalid TOML: {e}")))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(contents: &str) -> HttpResult<Self> {
+        serde_yaml::from_str(contents).map_err(|e| HttpError::Config(format!("invalid YAML: {e}")))
+    }
+
+    /// Load a `.toml`, `.yaml`/`.yml` or `.json` profile file
+    pub fn from_file(path: impl AsRef<Path>) -> HttpResult<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            #[cfg(feature = "toml")]
+            "toml" => Self::from_toml_str(&contents),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Self::from_yaml_str(&contents),
+            "json" => serde_json::from_str(&contents)
+                .map_err(|e| HttpError::Config(format!("invalid JSON: {e}"))),
+            other => Err(HttpError::Config(format!(
+                "unsupported profile file type '{other}' for {} (is the matching feature enabled?)",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> HttpResult<Profile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| HttpError::Config(format!("no profile named '{name}'")))?
+            .resolve(name)
+    }
+
+    /// The profile named by `CORE_RS_PROFILE`, falling back to `default_profile`
+    pub fn active(&self) -> HttpResult<Profile> {
+        let name = std::env::var(PROFILE_ENV_VAR)
+            .ok()
+            .or_else(|| self.default_profile.clone())
+            .ok_or_else(|| {
+                HttpError::Config(format!(
+                    "no profile selected; set {PROFILE_ENV_VAR} or default_profile"
+                ))
+            })?;
+        self.profile(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_cover_production_only() {
+        assert_eq!(
+            Environment::preset("PROD"),
+            Some(Environment::prime_production())
+        );
+        assert_eq!(
+            Environment::preset("prime-production"),
+            Some(Environment::prime_production())
+        );
+        assert_eq!(Environment::preset("sandbox"), None);
+    }
+
+    #[test]
+    fn profile_urls_override_the_preset() {
+        let spec = ProfileSpec {
+            environment: Some("production".to_string()),
+            rest_base_url: Some("https://proxy.internal/v1".to_string()),
+            credentials: Some("prime-prod".to_string()),
+            ..Default::default()
+        };
+        let profile = spec.resolve("dev").unwrap();
+        assert_eq!(profile.environment.name, "production");
+        assert_eq!(
+            profile.environment.rest_base_url,
+            "https://proxy.internal/v1"
+        );
+        assert_eq!(
+            profile.environment.fix_endpoint.as_deref(),
+            Some(PRIME_PRODUCTION_FIX_ENDPOINT)
+        );
+        assert_eq!(profile.credentials.as_deref(), Some("prime-prod"));
+    }
+
+    #[test]
+    fn incomplete_or_unknown_profiles_are_config_errors() {
+        let unknown = ProfileSpec {
+            environment: Some("moon".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(unknown.resolve("x"), Err(HttpError::Config(_))));
+        assert!(matches!(
+            ProfileSpec::default().resolve("x"),
+            Err(HttpError::Config(_))
+        ));
+        assert!(matches!(
+            ProfileConfig::default().profile("missing"),
+            Err(HttpError::Config(_))
+        ));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_profiles_resolve_by_name() {
+        let config = ProfileConfig::from_toml_str(
+            r#"
+            default_profile = "sandbox"
+
+            [profiles.sandbox]
+            rest_base_url = "https://sandbox.example.com/v1"
+            credentials = "prime-sandbox"
+            "#,
+        )
+        .unwrap();
+        let profile = config.profile("sandbox").unwrap();
+        assert_eq!(
+            profile.environment.rest_base_url,
+            "https://sandbox.example.com/v1"
+        );
+        assert_eq!(profile.credentials.as_deref(), Some("prime-sandbox"));
+        assert!(ProfileConfig::from_toml_str("[profiles.x]\nbogus = 1").is_err());
+    }
+}
@@ -13,15 +13,215 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::http_headers::HttpHeaders;
+use crate::http_status_code::HttpStatusCode;
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Which time limit ended a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// A single attempt took longer than the per-attempt timeout
+    Attempt,
+    /// The overall deadline across all attempts and backoff was reached
+    Deadline,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutKind::Attempt => write!(f, "per-attempt timeout"),
+            TimeoutKind::Deadline => write!(f, "overall deadline"),
+        }
+    }
+}
+
+/// Details about the request that failed, attached by the client
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The idempotency key sent with every attempt, so the outcome can be checked later
+    pub idempotency_key: Option<String>,
+    /// Our correlation id, sent in the configured correlation header
+    pub correlation_id: Option<String>,
+    /// The server's id for the request, to quote in support tickets
+    pub request_id: Option<String>,
+    /// The headers an error response's request was sent with, including those the client
+    /// added (defaults, signatures)
+    pub request_headers: Option<HttpHeaders>,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details: Vec<String> = [
+            ("idempotency key", &self.idempotency_key),
+            ("correlation id", &self.correlation_id),
+            ("request id", &self.request_id),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.as_ref().map(|value| format!("{label} {value}")))
+        .collect();
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum HttpError {
+    #[cfg(feature = "reqwest")]
     #[error("HTTP client error: {0}")]
     Reqwest(#[from] reqwest::Error),
+    /// A failure reported by a transport other than reqwest
+    #[error("HTTP transport error: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
     #[error("JSON deserialization error: {0}")]
     Deserialization(#[from] serde_json::Error),
+    #[error("HTTP error {status}: {}", String::from_utf8_lossy(body))]
+    Status {
+        status: HttpStatusCode,
+        headers: HttpHeaders,
+        body: Bytes,
+    },
+    #[error("Unexpected content type: expected JSON, got {0}")]
+    UnexpectedContentType(String),
+    #[error("Request timed out: {kind} of {limit:?} exceeded")]
+    Timeout { kind: TimeoutKind, limit: Duration },
+    #[error("TLS configuration error: {0}")]
+    Tls(String),
+    #[error("Certificate pin mismatch for {host}: server key sha256/{observed} is not pinned")]
+    PinMismatch { host: String, observed: String },
+    #[error("Configuration error: {0}")]
+    Config(String),
+    #[error("Invalid header '{name}': {reason}")]
+    InvalidHeader { name: String, reason: String },
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("Audit log chain broken at line {line}: {reason}")]
+    AuditChain { line: u64, reason: String },
+    #[error("Malformed JSON element at byte offset {offset}: {source}")]
+    JsonElement {
+        offset: u64,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Malformed JSON stream at byte offset {offset}: {message}")]
+    JsonStream { offset: u64, message: String },
+    #[error("{source}{context}")]
+    Context {
+        context: Box<ErrorContext>,
+        #[source]
+        source: Box<HttpError>,
+    },
+    /// An error shared by several callers, e.g. the waiters of a coalesced request
+    #[error("{0}")]
+    Shared(Arc<HttpError>),
     // You can add more variants here as needed
     #[error("Custom error: {0}")]
     Custom(String),
 }
 
+impl HttpError {
+    /// Get the HTTP status code if this error was caused by an error response
+    pub fn status(&self) -> Option<HttpStatusCode> {
+        match self.root() {
+            HttpError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Decode the body of an error response into a typed API error.
+    ///
+    /// Fails if this is not an error response, if the response has a non-JSON
+    /// `Content-Type`, or if the body doesn't match `E`.
+    pub fn decode_body<E: DeserializeOwned>(&self) -> HttpResult<E> {
+        match self.root() {
+            HttpError::Status {
+                status,
+                headers,
+                body,
+            } => crate::http_response::decode_body(
+                *status,
+                headers.content_type(),
+                body,
+                "<error response>",
+            ),
+            other => Err(HttpError::Custom(format!(
+                "No error response body to decode: {other}"
+            ))),
+        }
+    }
+
+    /// The headers of the error response, if this error was caused by one
+    pub fn response_headers(&self) -> Option<&HttpHeaders> {
+        match self.root() {
+            HttpError::Status { headers, .. } => Some(headers),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, looking through any attached context
+    pub fn root(&self) -> &HttpError {
+        match self {
+            HttpError::Context { source, .. } => source.root(),
+            HttpError::Shared(shared) => shared.root(),
+            other => other,
+        }
+    }
+
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            HttpError::Context { context, .. } => Some(context),
+            HttpError::Shared(shared) => shared.context(),
+            _ => None,
+        }
+    }
+
+    /// The idempotency key the failed request was sent with
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.context()?.idempotency_key.as_deref()
+    }
+
+    /// The correlation id the failed request was sent with
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.context()?.correlation_id.as_deref()
+    }
+
+    /// The server's request id, when the failure was an error response that carried one
+    pub fn request_id(&self) -> Option<&str> {
+        self.context()?.request_id.as_deref()
+    }
+
+    /// The headers the request behind an error response was sent with
+    pub fn request_headers(&self) -> Option<&HttpHeaders> {
+        self.context()?.request_headers.as_ref()
+    }
+
+    /// Attach request details, merging into existing context rather than nesting it
+    pub(crate) fn with_context(self, update: impl FnOnce(&mut ErrorContext)) -> HttpError {
+        match self {
+            HttpError::Context {
+                mut context,
+                source,
+            } => {
+                update(&mut context);
+                HttpError::Context { context, source }
+            }
+            other => {
+                let mut context = ErrorContext::default();
+                update(&mut context);
+                HttpError::Context {
+                    context: Box::new(context),
+                    source: Box::new(other),
+                }
+            }
+        }
+    }
+}
+
 pub type HttpResult<T> = Result<T, HttpError>;
@@ -0,0 +1,216 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt;
+
+/// A type map for passing data between interceptors, middleware and the caller, such as a
+/// trace id, the route template or a rate-limit key.
+///
+/// Values are keyed by type, so wrap shared types like `String` in a newtype. Extensions
+/// are in-process only: they are not sent on the wire, not serialized and not compared.
+/// `Extensions` has no `PartialEq`; `HttpRequest` leaves them out of its equality.
+#[derive(Clone, Default)]
+pub struct Extensions(http::Extensions);
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value, returning the previous value of the same type
+    pub fn insert<T: Clone + Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.0.insert(value)
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.0.get()
+    }
+
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.0.get_mut()
+    }
+
+    pub fn get_or_insert_with<T: Clone + Send + Sync + 'static>(
+        &mut self,
+        f: impl FnOnce() -> T,
+    ) -> &mut T {
+        self.0.get_or_insert_with(f)
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.0.remove()
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.0.get::<T>().is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Add every value from `other`, replacing values of the same type
+    pub fn extend(&mut self, other: Extensions) {
+        self.0.extend(other.0);
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl From<http::Extensions> for Extensions {
+    fn from(extensions: http::Extensions) -> Self {
+        Self(extensions)
+    }
+}
+
+impl From<Extensions> for http::Extensions {
+    fn from(extensions: Extensions) -> Self {
+        extensions.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{HttpError, HttpResult};
+    use crate::http_client::{HttpClient, ReqwestClient};
+    use crate::http_method::HttpMethod;
+    use crate::http_request::{HttpRequest, RetryPolicy};
+    use crate::http_response::HttpResponse;
+    use crate::http_status_code::HttpStatusCode;
+    use crate::http_url::HttpUrl;
+    use crate::interceptor::{PostRequestInterceptor, PreRequestInterceptor};
+    use crate::transport::Transport;
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TraceId(&'static str);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Route(&'static str);
+
+    // Fails the first attempt, then answers 200; records the trace id each attempt saw
+    #[derive(Default)]
+    struct FlakyTransport {
+        seen: Mutex<Vec<Option<TraceId>>>,
+    }
+
+    #[async_trait]
+    impl Transport for Arc<FlakyTransport> {
+        async fn send(&self, request: &HttpRequest, _: HttpUrl) -> HttpResult<HttpResponse> {
+            let attempt = {
+                let mut seen = self.seen.lock().unwrap();
+                seen.push(request.extensions().get::<TraceId>().cloned());
+                seen.len()
+            };
+            if attempt == 1 {
+                return Err(HttpError::Custom("connection reset".to_string()));
+            }
+            Ok(HttpResponse::new(HttpStatusCode::Ok))
+        }
+    }
+
+    // Adds the route before sending and checks the response still carries it afterwards
+    struct RouteTagger;
+
+    #[async_trait]
+    impl PreRequestInterceptor for RouteTagger {
+        async fn intercept(&self, request: &mut HttpRequest) {
+            assert_eq!(request.extensions().get(), Some(&TraceId("t-1")));
+            request.extensions_mut().insert(Route("/orders"));
+        }
+    }
+
+    #[async_trait]
+    impl PostRequestInterceptor for RouteTagger {
+        async fn intercept(&self, response: &mut HttpResponse) {
+            assert_eq!(response.extensions().get(), Some(&Route("/orders")));
+            response.extensions_mut().insert(TraceId("t-2"));
+        }
+    }
+
+    #[test]
+    fn values_are_keyed_by_type() {
+        let mut extensions = Extensions::new();
+        assert!(extensions.is_empty());
+        assert_eq!(extensions.insert(TraceId("a")), None);
+        assert_eq!(extensions.insert(TraceId("b")), Some(TraceId("a")));
+        extensions.insert(Route("/orders"));
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions.get::<TraceId>(), Some(&TraceId("b")));
+        assert!(extensions.contains::<Route>());
+
+        let mut other = Extensions::new();
+        other.insert(TraceId("c"));
+        extensions.extend(other);
+        assert_eq!(extensions.remove::<TraceId>(), Some(TraceId("c")));
+        assert_eq!(
+            extensions.get_or_insert_with(|| TraceId("d")),
+            &TraceId("d")
+        );
+        extensions.clear();
+        assert!(extensions.is_empty());
+    }
+
+    #[test]
+    fn requests_compare_without_their_extensions() {
+        let request = HttpRequest::new(HttpMethod::Get, "/orders").unwrap();
+        let tagged = request.clone().with_extension(TraceId("a"));
+        assert_eq!(request, tagged);
+        assert_ne!(request, tagged.with_path("/fills"));
+    }
+
+    #[tokio::test]
+    async fn extensions_reach_retries_interceptors_and_the_response() {
+        let transport = Arc::new(FlakyTransport::default());
+        let client = ReqwestClient::builder()
+            .base_url("https://api.example.com")
+            .transport(transport.clone())
+            .pre_interceptor(Arc::new(RouteTagger))
+            .post_interceptor(Arc::new(RouteTagger))
+            .default_retry_policy(RetryPolicy {
+                max_attempts: 2,
+                backoff_millis: 0,
+            })
+            .build()
+            .unwrap();
+        let request = HttpRequest::new(HttpMethod::Get, "/orders")
+            .unwrap()
+            .with_extension(TraceId("t-1"));
+
+        let response = client.execute(request).await.unwrap();
+        assert_eq!(
+            *transport.seen.lock().unwrap(),
+            [Some(TraceId("t-1")), Some(TraceId("t-1"))]
+        );
+        assert_eq!(response.extensions().get(), Some(&Route("/orders")));
+        assert_eq!(response.extensions().get(), Some(&TraceId("t-2")));
+    }
+}
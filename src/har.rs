@@ -0,0 +1,501 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_client::HttpClient;
+use crate::http_headers::HttpHeaders;
+use crate::http_request::{HttpRequest, RequestBody};
+use crate::http_response::HttpResponse;
+use crate::multipart::PartBody;
+use async_trait::async_trait;
+use base64::Engine;
+use bytes::Bytes;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// Wraps a client and records every exchange in a HAR 1.2 file that opens in browser
+/// devtools and other HAR viewers.
+///
+/// Exchanges are kept in memory and written by [`HarRecorder::save`], and by dropping the
+/// recorder if anything was recorded since the last save. Only the latest
+/// [`DEFAULT_MAX_ENTRIES`] exchanges are kept (see [`HarRecorder::with_max_entries`]); older
+/// ones are dropped and counted in the log's `comment`. Request headers are recorded as
+/// the inner client sent them, including defaults and auth signatures, when it reports them
+/// (as [`crate::http_client::ReqwestClient`] does). Sensitive header values are masked
+/// unless [`HarRecorder::with_redaction`] turns that off.
+pub struct HarRecorder<C> {
+    inner: C,
+    path: PathBuf,
+    redact: bool,
+    max_entries: usize,
+    recording: Mutex<Recording>,
+}
+
+/// How many exchanges a [`HarRecorder`] keeps unless configured otherwise
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+#[derive(Default)]
+struct Recording {
+    entries: VecDeque<Value>,
+    // Entries pushed out by the cap
+    dropped: u64,
+    unsaved: bool,
+}
+
+impl Recording {
+    fn document(&self) -> Value {
+        let mut log = json!({
+            "version": "1.2",
+            "creator": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            "pages": [],
+            "entries": self.entries,
+        });
+        if self.dropped > 0 {
+            log["comment"] = format!(
+                "{} earlier entries were dropped to stay within the entry limit",
+                self.dropped
+            )
+            .into();
+        }
+        json!({ "log": log })
+    }
+}
+
+impl<C: HttpClient> HarRecorder<C> {
+    pub fn new(inner: C, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            redact: true,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            recording: Mutex::new(Recording::default()),
+        }
+    }
+
+    /// Whether sensitive header values are masked in the file (default true)
+    pub fn with_redaction(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// Keep at most this many exchanges, dropping the oldest first (at least one is kept)
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The exchanges recorded so far as a HAR document
+    pub async fn har(&self) -> Value {
+        self.recording.lock().await.document()
+    }
+
+    /// Write the recorded exchanges to the file
+    pub async fn save(&self) -> HttpResult<()> {
+        let mut recording = self.recording.lock().await;
+        let har = serde_json::to_vec_pretty(&recording.document())?;
+        tokio::fs::write(&self.path, har).await?;
+        recording.unsaved = false;
+        Ok(())
+    }
+
+    fn headers(&self, headers: &HttpHeaders) -> Value {
+        let headers = if self.redact {
+            headers.redacted()
+        } else {
+            headers.clone()
+        };
+        headers
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect()
+    }
+
+    fn request_entry(&self, request: &HttpRequest, sent: Option<&HttpHeaders>, url: &str) -> Value {
+        let query: Vec<Value> = url::Url::parse(url)
+            .map(|url| {
+                url.query_pairs()
+                    .map(|(name, value)| json!({ "name": name, "value": value }))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut entry = json!({
+            "method": request.method.as_str(),
+            "url": url,
+            "httpVersion": "",
+            "cookies": [],
+            "headers": self.headers(sent.unwrap_or(&request.headers)),
+            "queryString": query,
+            "headersSize": -1,
+            "bodySize": request.body_bytes().map_or(-1, |body| body.len() as i64),
+        });
+        let post_data = match &request.body {
+            RequestBody::Empty => None,
+            RequestBody::Json(json) => Some(json!({
+                "mimeType": request.headers.content_type().unwrap_or("application/json"),
+                "text": json.to_string(),
+            })),
+            RequestBody::Bytes(bytes) => Some(json!({
+                "mimeType": request.headers.content_type().unwrap_or("application/octet-stream"),
+                "text": String::from_utf8_lossy(bytes),
+            })),
+            RequestBody::Multipart(multipart) => {
+                let params: Vec<Value> = multipart
+                    .parts()
+                    .iter()
+                    .map(|part| {
+                        let mut param = json!({ "name": part.name() });
+                        match part.body() {
+                            PartBody::Bytes(bytes) if part.filename().is_none() => {
+                                param["value"] = String::from_utf8_lossy(bytes).into();
+                            }
+                            PartBody::File(path) => {
+                                param["fileName"] = part
+                                    .filename()
+                                    .map_or_else(|| path.display().to_string(), str::to_string)
+                                    .into();
+                            }
+                            _ => param["fileName"] = part.filename().unwrap_or_default().into(),
+                        }
+                        if let Some(content_type) = part.content_type() {
+                            param["contentType"] = content_type.into();
+                        }
+                        param
+                    })
+                    .collect();
+                Some(json!({ "mimeType": multipart.content_type(), "params": params }))
+            }
+        };
+        if let Some(post_data) = post_data {
+            entry["postData"] = post_data;
+        }
+        entry
+    }
+
+    fn response_entry(&self, result: &HttpResult<HttpResponse>) -> Value {
+        match result {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                json!({
+                    "status": status,
+                    "statusText": status_text(status),
+                    "httpVersion": "",
+                    "cookies": [],
+                    "headers": self.headers(response.headers()),
+                    "content": content(response.body(), response.content_type(), response.content_length()),
+                    "redirectURL": response.header("location").unwrap_or_default(),
+                    "headersSize": -1,
+                    "bodySize": response.content_length().map_or(-1, |length| length as i64),
+                })
+            }
+            Err(e) => {
+                let (status, headers, body) = match e.root() {
+                    HttpError::Status {
+                        status,
+                        headers,
+                        body,
+                    } => (status.as_u16(), Some(headers), Some(body)),
+                    _ => (0, None, None),
+                };
+                let mut entry = json!({
+                    "status": status,
+                    "statusText": status_text(status),
+                    "httpVersion": "",
+                    "cookies": [],
+                    "headers": headers.map_or_else(|| json!([]), |headers| self.headers(headers)),
+                    "content": match body {
+                        Some(body) => content(
+                            Some(body),
+                            headers.and_then(HttpHeaders::content_type),
+                            Some(body.len() as u64),
+                        ),
+                        None => json!({ "size": 0, "mimeType": "" }),
+                    },
+                    "redirectURL": headers.and_then(|headers| headers.get("location")).unwrap_or_default(),
+                    "headersSize": -1,
+                    "bodySize": body.map_or(-1, |b| b.len() as i64),
+                });
+                if status == 0 {
+                    // Transport failures have no response; viewers show this custom field
+                    entry["_error"] = e.to_string().into();
+                }
+                entry
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> HttpClient for HarRecorder<C> {
+    async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse> {
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+        let recorded = request.clone();
+        let result = self.inner.execute(request).await;
+        let elapsed = started.elapsed().as_secs_f64() * 1000.0;
+
+        let (url, sent) = match &result {
+            Ok(response) => (
+                response.url().map(ToString::to_string),
+                response.request_headers(),
+            ),
+            Err(e) => (None, e.request_headers()),
+        };
+        let url = url.unwrap_or_else(|| recorded.display_url());
+        let entry = json!({
+            "startedDateTime": iso8601(started_at),
+            "time": elapsed,
+            "request": self.request_entry(&recorded, sent, &url),
+            "response": self.response_entry(&result),
+            "cache": {},
+            "timings": { "blocked": -1, "dns": -1, "connect": -1, "send": 0, "wait": elapsed, "receive": 0, "ssl": -1 },
+        });
+
+        let mut recording = self.recording.lock().await;
+        if recording.entries.len() >= self.max_entries {
+            recording.entries.pop_front();
+            recording.dropped += 1;
+        }
+        recording.entries.push_back(entry);
+        recording.unsaved = true;
+        result
+    }
+}
+
+// Save whatever wasn't saved explicitly; a failure here can only be logged
+impl<C> Drop for HarRecorder<C> {
+    fn drop(&mut self) {
+        let recording = self.recording.get_mut();
+        if !recording.unsaved {
+            return;
+        }
+        let written = serde_json::to_vec_pretty(&recording.document())
+            .map_err(std::io::Error::from)
+            .and_then(|har| std::fs::write(&self.path, har));
+        if let Err(_e) = written {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(path = %self.path.display(), error = %_e, "failed to write HAR file");
+        }
+    }
+}
+
+// Text bodies are recorded as-is, binary ones base64-encoded; streamed bodies are not read
+fn content(body: Option<&Bytes>, mime_type: Option<&str>, size: Option<u64>) -> Value {
+    let mut content = json!({
+        "size": size.unwrap_or_default(),
+        "mimeType": mime_type.unwrap_or_default(),
+    });
+    match body.map(|body| std::str::from_utf8(body).map_err(|_| body)) {
+        Some(Ok(text)) => content["text"] = text.into(),
+        Some(Err(bytes)) => {
+            content["text"] = base64::engine::general_purpose::STANDARD
+                .encode(bytes)
+                .into();
+            content["encoding"] = "base64".into();
+        }
+        None => content["comment"] = "streamed body not recorded".into(),
+    }
+    content
+}
+
+fn status_text(status: u16) -> &'static str {
+    http::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default()
+}
+
+// Format a time as an ISO 8601 UTC timestamp with milliseconds
+pub(crate) fn iso8601(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_method::HttpMethod;
+    use crate::http_status_code::HttpStatusCode;
+    use std::time::Duration;
+
+    // Adds a signing header the way an inner client would, and fails `/missing` with 404
+    struct Signing;
+
+    #[async_trait]
+    impl HttpClient for Signing {
+        async fn execute(&self, mut request: HttpRequest) -> HttpResult<HttpResponse> {
+            request.headers.insert("cb-access-sign", "signature")?;
+            request.headers.insert("user-agent", "core_rs")?;
+            if request.path.as_deref() == Some("/missing") {
+                let mut headers = HttpHeaders::new();
+                headers.insert("content-type", "application/json")?;
+                headers.insert("set-cookie", "session=secret")?;
+                headers.insert("x-request-id", "req-404")?;
+                let error = HttpError::Status {
+                    status: HttpStatusCode::NotFound,
+                    headers,
+                    body: Bytes::from_static(b"{\"message\":\"not found\"}"),
+                };
+                return Err(error.with_context(|context| {
+                    context.request_headers = Some(request.headers.clone());
+                }));
+            }
+            Ok(HttpResponse::new(HttpStatusCode::Ok)
+                .with_header("content-type", "application/json")
+                .with_body("{}")
+                .with_request_headers(Some(request.headers)))
+        }
+    }
+
+    fn temp_file() -> PathBuf {
+        std::env::temp_dir().join(format!("core_rs-{}.har", uuid::Uuid::new_v4()))
+    }
+
+    fn header<'a>(headers: &'a Value, name: &str) -> Option<&'a str> {
+        headers.as_array()?.iter().find(|h| h["name"] == name)?["value"].as_str()
+    }
+
+    #[test]
+    fn iso8601_formats_utc_with_milliseconds() {
+        let cases = [
+            (0, 0, "1970-01-01T00:00:00.000Z"),
+            (951_782_400, 5, "2000-02-29T00:00:00.005Z"),
+            (1_709_208_000, 999, "2024-02-29T12:00:00.999Z"),
+            (4_102_444_799, 0, "2099-12-31T23:59:59.000Z"),
+        ];
+        for (secs, millis, expected) in cases {
+            let time = UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_millis(millis);
+            assert_eq!(iso8601(time), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn records_headers_added_by_the_inner_client() {
+        let path = temp_file();
+        let recorder = HarRecorder::new(Signing, &path);
+        let request = HttpRequest::new(HttpMethod::Get, "/orders").unwrap();
+        recorder.execute(request).await.unwrap();
+        let request = HttpRequest::new(HttpMethod::Get, "/missing").unwrap();
+        assert!(recorder.execute(request).await.is_err());
+
+        let har = recorder.har().await;
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        for entry in entries {
+            let headers = &entry["request"]["headers"];
+            assert_eq!(header(headers, "user-agent"), Some("core_rs"));
+            assert_eq!(header(headers, "cb-access-sign"), Some("***"));
+        }
+        assert_eq!(entries[1]["response"]["status"], 404);
+        assert_eq!(
+            entries[1]["response"]["content"]["text"],
+            "{\"message\":\"not found\"}"
+        );
+        drop(recorder);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn error_responses_keep_their_headers_redacted() {
+        let path = temp_file();
+        let recorder = HarRecorder::new(Signing, &path);
+        let request = HttpRequest::new(HttpMethod::Get, "/missing").unwrap();
+        assert!(recorder.execute(request).await.is_err());
+
+        let har = recorder.har().await;
+        let response = &har["log"]["entries"][0]["response"];
+        assert_eq!(
+            header(&response["headers"], "x-request-id"),
+            Some("req-404")
+        );
+        assert_eq!(header(&response["headers"], "set-cookie"), Some("***"));
+        assert_eq!(response["content"]["mimeType"], "application/json");
+        drop(recorder);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn keeps_only_the_latest_entries() {
+        let path = temp_file();
+        let recorder = HarRecorder::new(Signing, &path).with_max_entries(2);
+        for path in ["/a", "/b", "/c"] {
+            let request = HttpRequest::new(HttpMethod::Get, path).unwrap();
+            recorder.execute(request).await.unwrap();
+        }
+        let har = recorder.har().await;
+        let urls: Vec<_> = har["log"]["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["request"]["url"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(urls, ["/b", "/c"]);
+        assert!(har["log"]["comment"]
+            .as_str()
+            .unwrap()
+            .starts_with("1 earlier"));
+        drop(recorder);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn writes_on_save_and_on_drop() {
+        let path = temp_file();
+        let recorder = HarRecorder::new(Signing, &path);
+        let request = HttpRequest::new(HttpMethod::Get, "/orders").unwrap();
+        recorder.execute(request).await.unwrap();
+        assert!(!path.exists(), "entries are buffered until saved");
+
+        recorder.save().await.unwrap();
+        let saved: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved["log"]["version"], "1.2");
+        assert_eq!(saved["log"]["entries"].as_array().unwrap().len(), 1);
+
+        let request = HttpRequest::new(HttpMethod::Get, "/orders").unwrap();
+        recorder.execute(request).await.unwrap();
+        drop(recorder);
+        let dropped: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(dropped["log"]["entries"].as_array().unwrap().len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
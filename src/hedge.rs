@@ -0,0 +1,363 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::HttpResult;
+use crate::http_client::HttpClient;
+use crate::http_method::HttpMethod;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::idempotency;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::time::{sleep, Duration, Instant};
+
+/// How long to wait for the first attempt before sending a hedge
+#[derive(Debug, Clone)]
+pub enum HedgeDelay {
+    Fixed(Duration),
+    /// Hedge once the first attempt is slower than this percentile (0-100) of recent
+    /// latencies. `initial` is used until `min_samples` latencies have been seen.
+    Percentile {
+        percentile: f64,
+        initial: Duration,
+        min_samples: usize,
+        window: usize,
+    },
+}
+
+impl HedgeDelay {
+    /// The p95 of the last 1000 requests, hedging after 100ms until 20 have completed
+    pub fn p95() -> Self {
+        HedgeDelay::Percentile {
+            percentile: 95.0,
+            initial: Duration::from_millis(100),
+            min_samples: 20,
+            window: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HedgePolicy {
+    pub delay: HedgeDelay,
+    /// Hedges allowed as a fraction of requests, e.g. `0.1` for at most one hedge per ten
+    pub budget_ratio: f64,
+    /// Hedges that can be sent back to back before the ratio applies
+    pub budget_burst: f64,
+}
+
+impl HedgePolicy {
+    pub fn new(delay: HedgeDelay) -> Self {
+        Self {
+            delay,
+            budget_ratio: 0.1,
+            budget_burst: 10.0,
+        }
+    }
+
+    pub fn with_budget(mut self, ratio: f64, burst: f64) -> Self {
+        self.budget_ratio = ratio.max(0.0);
+        self.budget_burst = burst.max(0.0);
+        self
+    }
+}
+
+/// Wraps a client so that slow reads get a second copy sent in parallel.
+///
+/// The first successful response wins and the other attempt is dropped, which cancels it.
+/// Only GET and HEAD are hedged, plus PUT and DELETE requests that carry an idempotency key
+/// or strategy; anything else (POST always) and requests with a one-shot body are passed
+/// through unchanged.
+pub struct Hedged<C> {
+    inner: C,
+    policy: HedgePolicy,
+    latencies: Mutex<VecDeque<Duration>>,
+    budget: Mutex<f64>,
+}
+
+impl<C: HttpClient> Hedged<C> {
+    pub fn new(inner: C, policy: HedgePolicy) -> Self {
+        let burst = policy.budget_burst;
+        Self {
+            inner,
+            policy,
+            latencies: Mutex::new(VecDeque::new()),
+            budget: Mutex::new(burst),
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn hedge_delay(&self) -> Duration {
+        match &self.policy.delay {
+            HedgeDelay::Fixed(delay) => *delay,
+            HedgeDelay::Percentile {
+                percentile,
+                initial,
+                min_samples,
+                ..
+            } => {
+                let latencies = self.latencies.lock().unwrap();
+                if latencies.len() < *min_samples.max(&1) {
+                    return *initial;
+                }
+                let mut sorted: Vec<Duration> = latencies.iter().copied().collect();
+                sorted.sort_unstable();
+                let rank = (percentile.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64)
+                    .round() as usize;
+                sorted[rank]
+            }
+        }
+    }
+
+    fn record_latency(&self, latency: Duration) {
+        if let HedgeDelay::Percentile { window, .. } = &self.policy.delay {
+            let mut latencies = self.latencies.lock().unwrap();
+            if latencies.len() >= (*window).max(1) {
+                latencies.pop_front();
+            }
+            latencies.push_back(latency);
+        }
+    }
+
+    fn deposit(&self) {
+        let mut budget = self.budget.lock().unwrap();
+        *budget = (*budget + self.policy.budget_ratio).min(self.policy.budget_burst);
+    }
+
+    fn try_withdraw(&self) -> bool {
+        let mut budget = self.budget.lock().unwrap();
+        if *budget >= 1.0 {
+            *budget -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> HttpClient for Hedged<C> {
+    async fn execute(&self, mut request: HttpRequest) -> HttpResult<HttpResponse> {
+        let keyed = request.idempotency().is_some() || request.idempotency_key().is_some();
+        let hedgeable = match request.method {
+            HttpMethod::Get | HttpMethod::Head => true,
+            HttpMethod::Put | HttpMethod::Delete => keyed,
+            _ => false,
+        };
+        if !hedgeable {
+            return self.inner.execute(request).await;
+        }
+        // Both copies must carry the same idempotency key
+        if request.idempotency().is_some() && request.idempotency_key().is_none() {
+            request.set_idempotency_key(idempotency::generate_key());
+        }
+        let Some(hedge) = request.try_clone() else {
+            return self.inner.execute(request).await;
+        };
+
+        self.deposit();
+        let started = Instant::now();
+        let primary = self.inner.execute(request);
+        tokio::pin!(primary);
+        tokio::select! {
+            result = &mut primary => {
+                if result.is_ok() {
+                    self.record_latency(started.elapsed());
+                }
+                return result;
+            }
+            _ = sleep(self.hedge_delay()) => {}
+        }
+
+        if !self.try_withdraw() {
+            let result = primary.await;
+            if result.is_ok() {
+                self.record_latency(started.elapsed());
+            }
+            return result;
+        }
+
+        let hedge = self.inner.execute(hedge);
+        tokio::pin!(hedge);
+        // Take the first success; if one copy fails, wait for the other
+        let result = tokio::select! {
+            result = &mut primary => match result {
+                Ok(response) => Ok(response),
+                Err(_) => hedge.await,
+            },
+            result = &mut hedge => match result {
+                Ok(response) => Ok(response),
+                Err(_) => primary.await,
+            },
+        };
+        if result.is_ok() {
+            self.record_latency(started.elapsed());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_status_code::HttpStatusCode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Answers call n after `delays[n]` (the last delay repeats), tagging the response with
+    // `x-call: n`, and counts calls that were dropped before they finished
+    struct Server {
+        delays: Vec<Duration>,
+        started: Mutex<Vec<Instant>>,
+        cancelled: AtomicUsize,
+    }
+
+    struct CancelGuard<'a> {
+        cancelled: &'a AtomicUsize,
+        finished: bool,
+    }
+
+    impl Drop for CancelGuard<'_> {
+        fn drop(&mut self) {
+            if !self.finished {
+                self.cancelled.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[async_trait]
+    impl HttpClient for Server {
+        async fn execute(&self, _: HttpRequest) -> HttpResult<HttpResponse> {
+            let call = {
+                let mut started = self.started.lock().unwrap();
+                started.push(Instant::now());
+                started.len() - 1
+            };
+            let mut guard = CancelGuard {
+                cancelled: &self.cancelled,
+                finished: false,
+            };
+            sleep(self.delays[call.min(self.delays.len() - 1)]).await;
+            guard.finished = true;
+            Ok(HttpResponse::new(HttpStatusCode::Ok).with_header("x-call", call.to_string()))
+        }
+    }
+
+    fn hedged(delays: &[u64], policy: HedgePolicy) -> Hedged<Server> {
+        Hedged::new(
+            Server {
+                delays: delays.iter().map(|ms| Duration::from_millis(*ms)).collect(),
+                started: Mutex::new(Vec::new()),
+                cancelled: AtomicUsize::new(0),
+            },
+            policy,
+        )
+    }
+
+    fn fixed(ms: u64) -> HedgePolicy {
+        HedgePolicy::new(HedgeDelay::Fixed(Duration::from_millis(ms)))
+    }
+
+    fn request(method: HttpMethod) -> HttpRequest {
+        HttpRequest::new(method, "/orders").unwrap()
+    }
+
+    fn calls(client: &Hedged<Server>) -> usize {
+        client.inner().started.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn fast_responses_are_not_hedged() {
+        let client = hedged(&[5], fixed(200));
+        let response = client.execute(request(HttpMethod::Get)).await.unwrap();
+        assert_eq!(response.header("x-call"), Some("0"));
+        assert_eq!(calls(&client), 1);
+    }
+
+    #[tokio::test]
+    async fn hedge_fires_after_the_delay_and_the_first_success_wins() {
+        let client = hedged(&[2000, 10], fixed(50));
+        let started = Instant::now();
+        let response = client.execute(request(HttpMethod::Get)).await.unwrap();
+        assert_eq!(response.header("x-call"), Some("1"));
+        assert!(started.elapsed() < Duration::from_millis(1000));
+
+        let times = client.inner().started.lock().unwrap().clone();
+        assert!(times[1] - times[0] >= Duration::from_millis(50));
+        // the slow primary was dropped, not left running
+        assert_eq!(client.inner().cancelled.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn budget_limits_hedges() {
+        let client = hedged(&[100], fixed(10).with_budget(0.0, 1.0));
+        client.execute(request(HttpMethod::Get)).await.unwrap();
+        assert_eq!(calls(&client), 2);
+        client.execute(request(HttpMethod::Get)).await.unwrap();
+        assert_eq!(calls(&client), 3);
+
+        // half a hedge per request, starting empty: every other slow request is hedged
+        let client = hedged(&[100], fixed(10).with_budget(0.5, 1.0));
+        *client.budget.lock().unwrap() = 0.0;
+        for _ in 0..4 {
+            client.execute(request(HttpMethod::Get)).await.unwrap();
+        }
+        assert_eq!(calls(&client), 6);
+    }
+
+    #[tokio::test]
+    async fn only_reads_and_keyed_puts_and_deletes_are_hedged() {
+        let cases = [
+            (request(HttpMethod::Head), 2),
+            (request(HttpMethod::Post), 1),
+            (request(HttpMethod::Post).with_idempotency_key("order-1"), 1),
+            (request(HttpMethod::Put), 1),
+            (request(HttpMethod::Delete), 1),
+            (request(HttpMethod::Put).with_idempotency_key("order-1"), 2),
+            (
+                request(HttpMethod::Delete).with_idempotency_key("order-1"),
+                2,
+            ),
+        ];
+        for (request, expected) in cases {
+            let method = request.method.clone();
+            let client = hedged(&[100], fixed(10));
+            client.execute(request).await.unwrap();
+            assert_eq!(calls(&client), expected, "{method:?}");
+        }
+    }
+
+    #[test]
+    fn percentile_delay_uses_recent_latencies() {
+        let client = hedged(
+            &[0],
+            HedgePolicy::new(HedgeDelay::Percentile {
+                percentile: 90.0,
+                initial: Duration::from_millis(500),
+                min_samples: 5,
+                window: 10,
+            }),
+        );
+        assert_eq!(client.hedge_delay(), Duration::from_millis(500));
+        for ms in 1..=20 {
+            client.record_latency(Duration::from_millis(ms));
+        }
+        // the window keeps 11..=20ms; the p90 of those is 19ms
+        assert_eq!(client.hedge_delay(), Duration::from_millis(19));
+    }
+}
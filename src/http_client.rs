@@ -13,40 +13,79 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use crate::error::{HttpError, HttpResult};
+use crate::correlation::{self, Correlation, REQUEST_ID_HEADER};
+use crate::endpoint::{Endpoint, EndpointSet};
+use crate::environment::Environment;
+use crate::error::{HttpError, HttpResult, TimeoutKind};
 use crate::http_headers::HttpHeaders;
 use crate::http_request::{HttpRequest, RetryPolicy};
 use crate::http_response::HttpResponse;
 use crate::http_url::HttpUrl;
+use crate::idempotency::{self, IdempotencyStrategy};
 use crate::interceptor::{PostRequestInterceptor, PreRequestInterceptor};
+#[cfg(feature = "reqwest")]
+use crate::reqwest_transport::ReqwestTransport;
+#[cfg(feature = "rustls")]
+use crate::tls::TlsConfig;
+use crate::transport::Transport;
 use async_trait::async_trait;
 use std::sync::Arc;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, timeout, Duration, Instant};
 
 #[async_trait]
 pub trait HttpClient: Send + Sync {
     async fn execute(&self, request: HttpRequest) -> HttpResult<HttpResponse>;
 }
 
+/// The HTTP client: resolves URLs, retries, fails over between endpoints, attaches
+/// idempotency keys and runs interceptors, sending each attempt through a [`Transport`]
+/// (reqwest unless another one is configured).
 pub struct ReqwestClient {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
+    default_headers: HttpHeaders,
     pre_interceptors: Vec<Arc<dyn PreRequestInterceptor>>,
     post_interceptors: Vec<Arc<dyn PostRequestInterceptor>>,
     default_retry_policy: Option<RetryPolicy>,
+    default_timeout: Option<Duration>,
+    default_deadline: Option<Duration>,
     base_url: Option<HttpUrl>,
+    endpoints: Option<Arc<EndpointSet>>,
+    allow_foreign_hosts: bool,
+    idempotency: Option<IdempotencyStrategy>,
+    correlation: Option<Correlation>,
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for ReqwestClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ReqwestClient {
+    #[cfg(feature = "reqwest")]
     pub fn new() -> Self {
         Self {
-            client: reqwest::Client::new(),
+            transport: Arc::new(ReqwestTransport::new()),
+            default_headers: HttpHeaders::new(),
             pre_interceptors: Vec::new(),
             post_interceptors: Vec::new(),
             default_retry_policy: None,
+            default_timeout: None,
+            default_deadline: None,
             base_url: None,
+            endpoints: None,
+            allow_foreign_hosts: false,
+            idempotency: None,
+            correlation: None,
         }
     }
 
+    pub fn builder() -> ReqwestClientBuilder {
+        ReqwestClientBuilder::new()
+    }
+
+    #[deprecated(note = "panics on an invalid URL; use `ReqwestClient::builder()` instead")]
     pub fn with_base_url(mut self, base_url: &str) -> Self {
         self.base_url = match HttpUrl::parse(base_url) {
             Ok(url) => Some(url),
@@ -58,12 +97,9 @@ impl ReqwestClient {
     }
 
     /// Set default headers for all requests. Overrides previous default headers if called multiple times.
+    #[deprecated(note = "set default headers on `ReqwestClient::builder()` instead")]
     pub fn with_default_headers(mut self, headers: HttpHeaders) -> Self {
-        let header_map = reqwest::header::HeaderMap::from(&headers);
-        self.client = reqwest::Client::builder()
-            .default_headers(header_map)
-            .build()
-            .expect("Failed to build reqwest client with default headers");
+        self.default_headers = headers;
         self
     }
 
@@ -72,6 +108,30 @@ impl ReqwestClient {
         self
     }
 
+    /// Per-attempt time limit for requests that don't set their own
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Overall time limit across all attempts and backoff for requests that don't set their own
+    pub fn with_default_deadline(mut self, deadline: Duration) -> Self {
+        self.default_deadline = Some(deadline);
+        self
+    }
+
+    /// Send an idempotency key with every POST and PATCH that doesn't choose its own
+    pub fn with_idempotency(mut self, strategy: IdempotencyStrategy) -> Self {
+        self.idempotency = Some(strategy);
+        self
+    }
+
+    /// Send a correlation id with every request and read the server's request id from responses
+    pub fn with_correlation(mut self, correlation: Correlation) -> Self {
+        self.correlation = Some(correlation);
+        self
+    }
+
     pub fn with_pre_interceptor(mut self, interceptor: Arc<dyn PreRequestInterceptor>) -> Self {
         self.pre_interceptors.push(interceptor);
         self
@@ -83,99 +143,739 @@ impl ReqwestClient {
     }
 }
 
+/// Collects client settings and builds the underlying `reqwest::Client` once.
+///
+/// Nothing is validated until [`ReqwestClientBuilder::build`], which reports invalid URLs,
+/// headers or proxy settings as errors instead of panicking. Connection, proxy, HTTP/2
+/// and TLS settings apply to the built-in reqwest transport only.
+#[derive(Default)]
+#[cfg_attr(not(feature = "reqwest"), allow(dead_code))]
+pub struct ReqwestClientBuilder {
+    transport: Option<Arc<dyn Transport>>,
+    base_url: Option<String>,
+    endpoints: Vec<Endpoint>,
+    endpoint_cooldown: Option<Duration>,
+    allow_foreign_hosts: bool,
+    default_headers: HttpHeaders,
+    invalid_header: Option<HttpError>,
+    user_agent: Option<String>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    tcp_keepalive: Option<Duration>,
+    proxy: Option<String>,
+    no_proxy: bool,
+    http2_prior_knowledge: bool,
+    http2_keep_alive_interval: Option<Duration>,
+    http2_adaptive_window: bool,
+    default_retry_policy: Option<RetryPolicy>,
+    default_timeout: Option<Duration>,
+    default_deadline: Option<Duration>,
+    idempotency: Option<IdempotencyStrategy>,
+    correlation: Option<Correlation>,
+    #[cfg(feature = "rustls")]
+    tls: Option<TlsConfig>,
+    pre_interceptors: Vec<Arc<dyn PreRequestInterceptor>>,
+    post_interceptors: Vec<Arc<dyn PostRequestInterceptor>>,
+}
+
+impl ReqwestClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send requests through another backend instead of reqwest, such as `HyperTransport`
+    /// or `UreqTransport`
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Spread requests over several base URLs instead of a single `base_url`.
+    ///
+    /// Endpoints that fail with a transport error, timeout or 5xx are avoided for a cooldown,
+    /// and retries go to a different endpoint where one is available. A 5xx is only retried
+    /// for idempotent methods or requests carrying an idempotency key. Pre-request
+    /// interceptors run once against the first endpoint chosen, so endpoints should share
+    /// the same path prefix when requests are signed.
+    pub fn endpoints(mut self, endpoints: Vec<Endpoint>) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// How long a failed endpoint is avoided; doubles with consecutive failures (default 30s)
+    pub fn endpoint_cooldown(mut self, cooldown: Duration) -> Self {
+        self.endpoint_cooldown = Some(cooldown);
+        self
+    }
+
+    /// Allow requests with an absolute URL that points at a different host than the base URL
+    pub fn allow_foreign_hosts(mut self, allow: bool) -> Self {
+        self.allow_foreign_hosts = allow;
+        self
+    }
+
+    /// Send requests to the REST base URL of an environment or profile
+    pub fn environment(self, environment: &Environment) -> Self {
+        self.base_url(environment.rest_base_url.clone())
+    }
+
+    /// Add headers sent with every request. Can be called multiple times; later values win.
+    pub fn default_headers(mut self, headers: HttpHeaders) -> Self {
+        self.default_headers.extend(headers);
+        self
+    }
+
+    pub fn default_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        if let Err(e) = self.default_headers.insert(name, value) {
+            self.invalid_header.get_or_insert(e);
+        }
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Time limit for establishing a connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Time limit for each read from the connection, reset after every successful read
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Route all traffic through the given proxy URL
+    pub fn proxy(mut self, proxy_url: impl Into<String>) -> Self {
+        self.proxy = Some(proxy_url.into());
+        self
+    }
+
+    /// Ignore system proxy settings such as `HTTPS_PROXY`
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2_prior_knowledge = true;
+        self
+    }
+
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2_adaptive_window = enabled;
+        self
+    }
+
+    /// Retry policy for requests that don't set their own. Only idempotent methods and
+    /// requests with an idempotency key are retried.
+    pub fn default_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.default_retry_policy = Some(policy);
+        self
+    }
+
+    /// Per-attempt time limit for requests that don't set their own
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Overall time limit across all attempts and backoff for requests that don't set their own
+    pub fn default_deadline(mut self, deadline: Duration) -> Self {
+        self.default_deadline = Some(deadline);
+        self
+    }
+
+    /// Send an idempotency key with every POST and PATCH that doesn't choose its own.
+    ///
+    /// With [`IdempotencyStrategy::JsonField`], requests without a JSON object body get the
+    /// key in the `Idempotency-Key` header instead.
+    pub fn idempotency(mut self, strategy: IdempotencyStrategy) -> Self {
+        self.idempotency = Some(strategy);
+        self
+    }
+
+    /// Send a correlation id with every request and read the server's request id from responses
+    pub fn correlation(mut self, correlation: Correlation) -> Self {
+        self.correlation = Some(correlation);
+        self
+    }
+
+    /// Use rustls with custom roots, a client identity and/or public key pins
+    #[cfg(feature = "rustls")]
+    pub fn tls(mut self, config: TlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    pub fn pre_interceptor(mut self, interceptor: Arc<dyn PreRequestInterceptor>) -> Self {
+        self.pre_interceptors.push(interceptor);
+        self
+    }
+
+    pub fn post_interceptor(mut self, interceptor: Arc<dyn PostRequestInterceptor>) -> Self {
+        self.post_interceptors.push(interceptor);
+        self
+    }
+
+    pub fn build(self) -> HttpResult<ReqwestClient> {
+        if let Some(e) = self.invalid_header {
+            return Err(e);
+        }
+        let base_url = match &self.base_url {
+            Some(url) => Some(
+                HttpUrl::parse(url)
+                    .map_err(|e| HttpError::Config(format!("Invalid base URL '{url}': {e}")))?,
+            ),
+            None => None,
+        };
+        if base_url.is_some() && !self.endpoints.is_empty() {
+            return Err(HttpError::Config(
+                "set either a base URL or a list of endpoints, not both".to_string(),
+            ));
+        }
+        let endpoints = if self.endpoints.is_empty() {
+            None
+        } else {
+            let mut set = EndpointSet::new(self.endpoints.clone())?;
+            if let Some(cooldown) = self.endpoint_cooldown {
+                set = set.with_cooldown(cooldown);
+            }
+            Some(Arc::new(set))
+        };
+
+        let mut default_headers = self.default_headers.clone();
+        if let Some(user_agent) = &self.user_agent {
+            default_headers.insert("user-agent", user_agent.clone())?;
+        }
+
+        let transport =
+            match &self.transport {
+                Some(transport) => transport.clone(),
+                #[cfg(feature = "reqwest")]
+                None => Arc::new(ReqwestTransport::from_client(self.reqwest_client()?)),
+                #[cfg(not(feature = "reqwest"))]
+                None => return Err(HttpError::Config(
+                    "no transport configured: enable the `reqwest` feature or call `transport()`"
+                        .to_string(),
+                )),
+            };
+
+        Ok(ReqwestClient {
+            transport,
+            default_headers,
+            pre_interceptors: self.pre_interceptors,
+            post_interceptors: self.post_interceptors,
+            default_retry_policy: self.default_retry_policy,
+            default_timeout: self.default_timeout,
+            default_deadline: self.default_deadline,
+            base_url,
+            endpoints,
+            allow_foreign_hosts: self.allow_foreign_hosts,
+            idempotency: self.idempotency,
+            correlation: self.correlation,
+        })
+    }
+
+    #[cfg(feature = "reqwest")]
+    fn reqwest_client(&self) -> HttpResult<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(interval) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        if self.no_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(proxy_url) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(interval) = self.http2_keep_alive_interval {
+            builder = builder.http2_keep_alive_interval(interval);
+        }
+        if self.http2_adaptive_window {
+            builder = builder.http2_adaptive_window(true);
+        }
+        #[cfg(feature = "rustls")]
+        if let Some(tls) = &self.tls {
+            builder = builder.use_preconfigured_tls(tls.build()?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
 #[async_trait]
 impl HttpClient for ReqwestClient {
     async fn execute(&self, mut request: HttpRequest) -> HttpResult<HttpResponse> {
-        // If base_url is set, join it with the request path (if relative) - do this FIRST
-        if let Some(base_url) = &self.base_url {
-            if let Some(ref path) = request.path {
-                match base_url.join(path) {
-                    Ok(full_url) => {
-                        let mut_req = request.as_mut_reqwest();
-                        *mut_req.url_mut() = full_url.0;
-                    }
+        // Both ids are attached once, before signing, so every attempt carries the same ones
+        let correlation_id = match &self.correlation {
+            Some(correlation) => Some(correlation.apply(&mut request)?),
+            None => None,
+        };
+        let key = idempotency::assign(&mut request, self.idempotency.as_ref())?;
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "http_request",
+            method = %request.method,
+            path = request.path.as_deref().unwrap_or_default(),
+            correlation_id = correlation_id.as_deref(),
+            request_id = tracing::field::Empty,
+        );
+        let sent = self.send(request);
+        #[cfg(feature = "tracing")]
+        let sent = tracing::Instrument::instrument(sent, span.clone());
+        let result = match sent.await {
+            Ok(mut response) => {
+                if let Some(key) = key {
+                    response = response.with_idempotency_key(key);
+                }
+                if let Some(id) = correlation_id {
+                    response = response.with_correlation_id(id);
+                }
+                Ok(response)
+            }
+            Err(e) if key.is_some() || correlation_id.is_some() => Err(e.with_context(|context| {
+                context.idempotency_key = key;
+                context.correlation_id = correlation_id;
+            })),
+            Err(e) => Err(e),
+        };
+        #[cfg(feature = "tracing")]
+        log_outcome(&span, &result);
+        result
+    }
+}
+
+// Record the server's request id on the span and log how the request ended
+#[cfg(feature = "tracing")]
+fn log_outcome(span: &tracing::Span, result: &HttpResult<HttpResponse>) {
+    match result {
+        Ok(response) => {
+            if let Some(id) = response.request_id() {
+                span.record("request_id", id);
+            }
+            tracing::debug!(
+                parent: span,
+                status = response.status().as_u16(),
+                attempts = response.attempts(),
+                elapsed_ms = response.elapsed().as_millis() as u64,
+                "request completed"
+            );
+        }
+        Err(e) => {
+            if let Some(id) = e.request_id() {
+                span.record("request_id", id);
+            }
+            tracing::warn!(
+                parent: span,
+                status = e.status().map(|status| status.as_u16()),
+                error = %e,
+                "request failed"
+            );
+        }
+    }
+}
+
+impl ReqwestClient {
+    async fn send(&self, mut request: HttpRequest) -> HttpResult<HttpResponse> {
+        let started = Instant::now();
+        let mut tried: Vec<usize> = Vec::new();
+        let mut endpoint = self.endpoints.as_ref().map(|set| set.select(&tried));
+        let base_url = match (&self.endpoints, endpoint) {
+            (Some(set), Some(index)) => Some(&set.endpoint(index).url),
+            _ => self.base_url.as_ref(),
+        };
+        // Resolve the request path against the base URL (keeping any base path prefix) - do this FIRST
+        if let Some(ref path) = request.path {
+            let resolved = match base_url {
+                Some(base_url) => Some(base_url.resolve(path, self.allow_foreign_hosts)),
+                None if HttpUrl::is_absolute(path) => Some(HttpUrl::parse(path)),
+                None => None,
+            };
+            if let Some(resolved) = resolved {
+                match resolved {
+                    Ok(full_url) => request.set_url(full_url),
                     Err(e) => {
                         return Err(HttpError::Custom(format!(
-                            "Failed to join base_url and path: {e}"
+                            "Failed to resolve request path '{path}': {e}"
                         )));
                     }
                 }
             }
         }
 
+        // Default headers go in before signing; headers set on the request win
+        let mut defaults = self.default_headers.clone();
+        for name in request.headers.names() {
+            defaults.remove(&name);
+        }
+        request.headers.extend(defaults);
+
         // Pre-request interceptors (now they'll see the correct URL)
         for interceptor in &self.pre_interceptors {
             interceptor.intercept(&mut request).await;
         }
-
-        // Append query parameters if present
-        if let Some(ref params) = request.query_params {
-            let mut url = request.as_reqwest().url().clone();
-            {
-                let mut pairs = url.query_pairs_mut();
-                for (k, v) in params {
-                    pairs.append_pair(k, v);
-                }
-            }
-            let mut_req = request.as_mut_reqwest();
-            *mut_req.url_mut() = url;
-        }
-        // Set JSON body if present
-        if let Some(json) = &request.json_body {
-            let json_string = json.to_string();
-            let mut_req = request.as_mut_reqwest();
-            *mut_req.body_mut() = Some(reqwest::Body::from(json_string));
-            mut_req.headers_mut().insert(
-                reqwest::header::CONTENT_TYPE,
-                reqwest::header::HeaderValue::from_static("application/json"),
-            );
-        }
+        let Some(mut url) = request.url.clone() else {
+            return Err(HttpError::Config(format!(
+                "Cannot send request to '{}': no base URL is configured",
+                request.path.as_deref().unwrap_or_default()
+            )));
+        };
         let retry_policy = request
             .retry_policy
             .clone()
             .or_else(|| self.default_retry_policy.clone());
         let mut attempts = 0;
-        let max_attempts = retry_policy.as_ref().map_or(1, |p| p.max_attempts);
+        let max_attempts = if request.is_retryable() {
+            retry_policy.as_ref().map_or(1, |p| p.max_attempts)
+        } else {
+            1
+        };
         let backoff = retry_policy.as_ref().map_or(0, |p| p.backoff_millis);
+        // A failed attempt may still have reached the server, so only requests that are
+        // harmless to repeat are sent again
+        let replayable = request.method.is_idempotent() || request.idempotency_key().is_some();
+        let attempt_timeout = request.timeout.or(self.default_timeout);
+        let deadline = request.deadline.or(self.default_deadline);
         loop {
-            let reqwest_request = match request.as_reqwest().try_clone() {
-                Some(r) => r,
-                None => {
-                    return Err(HttpError::Custom(
-                        "Failed to clone request for retry".to_string(),
-                    ));
+            let (limit, timeout_kind) = attempt_limit(attempt_timeout, deadline, started)?;
+            // Retries move to an endpoint that hasn't been tried yet, if there is one
+            if let (Some(set), true) = (&self.endpoints, attempts > 0) {
+                let index = set.select(&tried);
+                if Some(index) != endpoint {
+                    url = self.endpoint_url(&set.endpoint(index).url, &request)?;
+                }
+                endpoint = Some(index);
+            }
+            if let Some(index) = endpoint {
+                if !tried.contains(&index) {
+                    tried.push(index);
                 }
+            }
+            let sent = self.transport.send(&request, request.full_url(&url));
+            let outcome = match limit {
+                Some(limit) => match timeout(limit, sent).await {
+                    Ok(outcome) => outcome,
+                    Err(_) => Err(HttpError::Timeout {
+                        kind: timeout_kind,
+                        limit: match timeout_kind {
+                            TimeoutKind::Attempt => limit,
+                            TimeoutKind::Deadline => deadline.unwrap_or(limit),
+                        },
+                    }),
+                },
+                None => sent.await,
             };
-            match self.client.execute(reqwest_request).await {
-                Ok(response) => {
-                    let status = response.status();
-                    if status.is_client_error() || status.is_server_error() {
-                        let body = response
-                            .text()
-                            .await
-                            .unwrap_or_else(|_| "<failed to read body>".to_string());
-                        return Err(HttpError::Custom(format!(
-                            "HTTP error {}: {}",
-                            status.as_u16(),
-                            body
-                        )));
+            match outcome {
+                Ok(mut http_response) => {
+                    let status = http_response.status();
+                    self.record_outcome(endpoint, !status.is_server_error());
+                    attempts += 1;
+                    if status.is_error() {
+                        let error = HttpError::Status {
+                            status,
+                            headers: http_response.headers().clone(),
+                            body: http_response.body().cloned().unwrap_or_default(),
+                        };
+                        // With several endpoints a 5xx is worth retrying elsewhere
+                        if status.is_server_error()
+                            && self.endpoints.is_some()
+                            && replayable
+                            && attempts < max_attempts
+                        {
+                            self.pause(backoff, deadline, started).await;
+                            continue;
+                        }
+                        let request_id = self.request_id(http_response.headers());
+                        return Err(error.with_context(|context| {
+                            context.request_id = request_id;
+                            context.request_headers = Some(request.headers.clone());
+                        }));
+                    }
+                    let request_id = self.request_id(http_response.headers());
+                    http_response = http_response
+                        .with_elapsed(started.elapsed())
+                        .with_attempts(attempts)
+                        .with_request_id(request_id)
+                        .with_request_headers(Some(request.headers.clone()));
+                    http_response
+                        .extensions_mut()
+                        .extend(request.extensions().clone());
+                    if let (Some(set), Some(index)) = (&self.endpoints, endpoint) {
+                        http_response =
+                            http_response.with_endpoint(set.endpoint(index).url.clone());
                     }
-                    let mut http_response = HttpResponse::new(response);
                     for interceptor in &self.post_interceptors {
                         interceptor.intercept(&mut http_response).await;
                     }
                     return Ok(http_response);
                 }
+                Err(
+                    e @ HttpError::Timeout {
+                        kind: TimeoutKind::Deadline,
+                        ..
+                    },
+                ) => return Err(e),
                 Err(e) => {
+                    self.record_outcome(endpoint, false);
                     attempts += 1;
-                    if attempts >= max_attempts {
-                        return Err(HttpError::from(e));
-                    }
-                    if backoff > 0 {
-                        sleep(Duration::from_millis(backoff)).await;
+                    if attempts >= max_attempts || !replayable {
+                        return Err(e);
                     }
+                    self.pause(backoff, deadline, started).await;
                 }
             }
         }
     }
+
+    // Build the full URL for a request against another endpoint
+    fn endpoint_url(&self, base: &HttpUrl, request: &HttpRequest) -> HttpResult<HttpUrl> {
+        let path = request.path.as_deref().unwrap_or_default();
+        base.resolve(path, self.allow_foreign_hosts)
+            .map_err(|e| HttpError::Custom(format!("Failed to resolve request path '{path}': {e}")))
+    }
+
+    // The server's request id, from the configured headers or `X-Request-Id`
+    fn request_id(&self, headers: &HttpHeaders) -> Option<String> {
+        match &self.correlation {
+            Some(correlation) => correlation.request_id(headers),
+            None => correlation::request_id(headers, &[REQUEST_ID_HEADER]),
+        }
+    }
+
+    fn record_outcome(&self, endpoint: Option<usize>, healthy: bool) {
+        if let (Some(set), Some(index)) = (&self.endpoints, endpoint) {
+            if healthy {
+                set.mark_success(index);
+            } else {
+                set.mark_failure(index);
+            }
+        }
+    }
+
+    // Wait out the retry backoff, never past the overall deadline
+    async fn pause(&self, backoff: u64, deadline: Option<Duration>, started: Instant) {
+        if backoff > 0 {
+            let mut pause = Duration::from_millis(backoff);
+            if let Some(deadline) = deadline {
+                pause = pause.min(deadline.saturating_sub(started.elapsed()));
+            }
+            sleep(pause).await;
+        }
+    }
+}
+
+// Pick the time limit for the next attempt and which limit it represents, failing once the
+// overall deadline has already passed
+fn attempt_limit(
+    attempt_timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    started: Instant,
+) -> HttpResult<(Option<Duration>, TimeoutKind)> {
+    let remaining = deadline.map(|d| d.saturating_sub(started.elapsed()));
+    match (attempt_timeout, remaining) {
+        (_, Some(Duration::ZERO)) => Err(HttpError::Timeout {
+            kind: TimeoutKind::Deadline,
+            limit: deadline.unwrap_or_default(),
+        }),
+        (Some(attempt), Some(remaining)) if attempt < remaining => {
+            Ok((Some(attempt), TimeoutKind::Attempt))
+        }
+        (_, Some(remaining)) => Ok((Some(remaining), TimeoutKind::Deadline)),
+        (attempt, None) => Ok((attempt, TimeoutKind::Attempt)),
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use super::*;
+    use crate::http_method::HttpMethod;
+    use crate::http_status_code::HttpStatusCode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn builder_reports_configuration_errors() {
+        let result = ReqwestClient::builder().base_url("not a url").build();
+        assert!(matches!(result, Err(HttpError::Config(_))));
+
+        let result = ReqwestClient::builder()
+            .base_url("https://a.example.com")
+            .endpoints(vec![Endpoint::new("https://b.example.com").unwrap()])
+            .build();
+        assert!(matches!(result, Err(HttpError::Config(_))));
+
+        let result = ReqwestClient::builder()
+            .default_header("bad name", "x")
+            .build();
+        assert!(matches!(result, Err(HttpError::InvalidHeader { .. })));
+
+        let result = ReqwestClient::builder().proxy("::not a proxy::").build();
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn missing_base_url_is_a_config_error() {
+        let client = ReqwestClient::builder().build().unwrap();
+        let request = HttpRequest::new(HttpMethod::Get, "/orders").unwrap();
+        assert!(matches!(
+            client.execute(request).await,
+            Err(HttpError::Config(_))
+        ));
+    }
+
+    // Answers 503 to the first request it sees and 200 to the rest
+    struct FailsOnce {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Transport for FailsOnce {
+        async fn send(&self, _: &HttpRequest, _: HttpUrl) -> HttpResult<HttpResponse> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(HttpResponse::new(if call == 0 {
+                HttpStatusCode::ServiceUnavailable
+            } else {
+                HttpStatusCode::Ok
+            }))
+        }
+    }
+
+    // Never answers within the 20ms attempt timeout used below
+    struct Hangs {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Transport for Hangs {
+        async fn send(&self, _: &HttpRequest, _: HttpUrl) -> HttpResult<HttpResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(HttpResponse::new(HttpStatusCode::Ok))
+        }
+    }
+
+    async fn send_until_timeout(request: HttpRequest) -> (HttpResult<HttpResponse>, usize) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = ReqwestClient::builder()
+            .base_url("https://api.example.com")
+            .default_retry_policy(RetryPolicy {
+                max_attempts: 3,
+                backoff_millis: 0,
+            })
+            .default_timeout(Duration::from_millis(20))
+            .transport(Hangs {
+                calls: calls.clone(),
+            })
+            .build()
+            .unwrap();
+        let result = client.execute(request).await;
+        (result, calls.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn timed_out_attempts_are_retried_only_when_replay_is_safe() {
+        let post = HttpRequest::new(HttpMethod::Post, "/orders").unwrap();
+        let (result, calls) = send_until_timeout(post).await;
+        assert!(matches!(result, Err(HttpError::Timeout { .. })));
+        assert_eq!(calls, 1);
+
+        let keyed = HttpRequest::new(HttpMethod::Post, "/orders")
+            .unwrap()
+            .with_idempotency_key("order-1");
+        let (_, calls) = send_until_timeout(keyed).await;
+        assert_eq!(calls, 3);
+
+        let get = HttpRequest::new(HttpMethod::Get, "/orders").unwrap();
+        let (_, calls) = send_until_timeout(get).await;
+        assert_eq!(calls, 3);
+    }
+
+    async fn send_with_failover(request: HttpRequest) -> (HttpResult<HttpResponse>, usize) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = ReqwestClient::builder()
+            .endpoints(vec![
+                Endpoint::new("https://a.example.com").unwrap(),
+                Endpoint::new("https://b.example.com").unwrap(),
+            ])
+            .default_retry_policy(RetryPolicy {
+                max_attempts: 3,
+                backoff_millis: 0,
+            })
+            .transport(FailsOnce {
+                calls: calls.clone(),
+            })
+            .build()
+            .unwrap();
+        let result = client.execute(request).await;
+        (result, calls.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn server_errors_fail_over_only_when_replay_is_safe() {
+        let get = HttpRequest::new(HttpMethod::Get, "/orders").unwrap();
+        let (result, calls) = send_with_failover(get).await;
+        assert!(result.is_ok());
+        assert_eq!(calls, 2);
+
+        let keyed = HttpRequest::new(HttpMethod::Post, "/orders")
+            .unwrap()
+            .with_idempotency_key("order-1");
+        let (result, calls) = send_with_failover(keyed).await;
+        assert!(result.is_ok());
+        assert_eq!(calls, 2);
+
+        let post = HttpRequest::new(HttpMethod::Post, "/orders").unwrap();
+        let (result, calls) = send_with_failover(post).await;
+        assert_eq!(
+            result.unwrap_err().status(),
+            Some(HttpStatusCode::ServiceUnavailable)
+        );
+        assert_eq!(calls, 1);
+    }
 }
@@ -13,30 +13,479 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use std::collections::HashMap;
+use crate::error::{HttpError, HttpResult};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Default)]
-pub struct HttpHeaders(pub HashMap<String, String>);
+/// Headers whose values are masked by default: credentials and Coinbase API signing headers
+pub const DEFAULT_SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "cb-access-key",
+    "cb-access-passphrase",
+    "cb-access-sign",
+    "x-cb-access-key",
+    "x-cb-access-passphrase",
+    "x-cb-access-signature",
+];
+
+const REDACTED: &str = "***";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct HeaderEntry {
+    name: String,
+    value: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    sensitive: bool,
+}
+
+/// An ordered, case-insensitive multi-map of header names to values.
+///
+/// Names are stored lowercase and validated together with their values on insert, so a
+/// header map built from it never drops entries. Values of sensitive headers (see
+/// [`DEFAULT_SENSITIVE_HEADERS`] and [`HttpHeaders::mark_sensitive`]) are masked by `Debug`
+/// and [`HttpHeaders::redacted`].
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<HeaderEntry>", into = "Vec<HeaderEntry>")]
+pub struct HttpHeaders {
+    entries: Vec<HeaderEntry>,
+    // Names marked with `mark_sensitive`, applied to values added later
+    sensitive_names: BTreeSet<String>,
+}
+
+/// Rate-limit state reported by the server in `RateLimit-*` or `X-RateLimit-*` headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// Time until the window resets. Reset values that look like Unix timestamps (seconds or
+    /// milliseconds) are converted to the time remaining until then.
+    pub reset: Option<Duration>,
+}
 
 impl HttpHeaders {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
+    }
+
+    /// Set a header, replacing any values it already has
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) -> HttpResult<()> {
+        let mut entry = entry(name.into(), value.into())?;
+        entry.sensitive |= self.sensitive_names.contains(&entry.name);
+        match self.entries.iter().position(|e| e.name == entry.name) {
+            Some(index) => {
+                let sensitive = entry.sensitive || self.entries[index].sensitive;
+                self.entries[index] = HeaderEntry { sensitive, ..entry };
+                let name = self.entries[index].name.clone();
+                let mut seen = 0;
+                self.entries.retain(|e| {
+                    if e.name != name {
+                        return true;
+                    }
+                    seen += 1;
+                    seen == 1
+                });
+            }
+            None => self.entries.push(entry),
+        }
+        Ok(())
+    }
+
+    /// Add a value, keeping the values the header already has
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) -> HttpResult<()> {
+        let mut entry = entry(name.into(), value.into())?;
+        entry.sensitive |= self.is_sensitive(&entry.name);
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Set a header and mask its value in debug output and redacted copies
+    pub fn insert_sensitive(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> HttpResult<()> {
+        let name = name.into();
+        self.insert(name.clone(), value)?;
+        self.mark_sensitive(&name);
+        Ok(())
+    }
+
+    /// Mask the values of a header, including values added later
+    pub fn mark_sensitive(&mut self, name: &str) {
+        self.sensitive_names.insert(name.to_ascii_lowercase());
+        for entry in &mut self.entries {
+            if entry.name.eq_ignore_ascii_case(name) {
+                entry.sensitive = true;
+            }
+        }
+    }
+
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        self.sensitive_names.contains(&name.to_ascii_lowercase())
+            || self
+                .entries
+                .iter()
+                .any(|e| e.sensitive && e.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The first value of a header
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|e| e.name.eq_ignore_ascii_case(name))
+            .map(|e| e.value.as_str())
+    }
+
+    /// Every value of a header, in insertion order
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |e| e.name.eq_ignore_ascii_case(name))
+            .map(|e| e.value.as_str())
+    }
+
+    /// Every value of a header joined into one comma-separated list
+    pub fn get_joined(&self, name: &str) -> Option<String> {
+        let values: Vec<&str> = self.get_all(name).collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Remove a header, returning its first value
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let first = self.get(name).map(str::to_string);
+        self.entries.retain(|e| !e.name.eq_ignore_ascii_case(name));
+        first
+    }
+
+    /// Add every header from `other`, replacing the values of headers present in both.
+    ///
+    /// A header stays sensitive if either side marks it so.
+    pub fn extend(&mut self, other: HttpHeaders) {
+        let sensitive: Vec<String> = other
+            .names()
+            .into_iter()
+            .filter(|name| self.is_sensitive(name))
+            .collect();
+        for name in other.names() {
+            self.entries.retain(|e| e.name != name);
+        }
+        self.entries.extend(other.entries);
+        self.sensitive_names.extend(other.sensitive_names);
+        for name in sensitive {
+            self.mark_sensitive(&name);
+        }
+    }
+
+    /// Name and value pairs in insertion order, with repeated headers listed once per value
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.value.as_str()))
+    }
+
+    /// Distinct header names in order of first appearance
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for entry in &self.entries {
+            if !names.contains(&entry.name) {
+                names.push(entry.name.clone());
+            }
+        }
+        names
     }
-    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
-        self.0.insert(key.into(), value.into());
+
+    /// Number of values, counting each value of a repeated header
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// A copy with the values of sensitive headers replaced by `***`
+    pub fn redacted(&self) -> HttpHeaders {
+        let entries = self
+            .entries
+            .iter()
+            .map(|e| HeaderEntry {
+                value: if e.sensitive {
+                    REDACTED.to_string()
+                } else {
+                    e.value.clone()
+                },
+                ..e.clone()
+            })
+            .collect();
+        HttpHeaders {
+            entries,
+            sensitive_names: self.sensitive_names.clone(),
+        }
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.get("content-type")
+    }
+
+    /// The media type without parameters, lowercased, e.g. `application/json`
+    pub fn media_type(&self) -> Option<String> {
+        let content_type = self.content_type()?;
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        Some(essence.to_ascii_lowercase())
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.get("content-length")?.trim().parse().ok()
+    }
+
+    pub fn date(&self) -> Option<SystemTime> {
+        self.http_date("date")
+    }
+
+    /// How long to wait before retrying, from delay-seconds or an HTTP date
+    pub fn retry_after(&self) -> Option<Duration> {
+        let value = self.get("retry-after")?.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        let at = httpdate::parse_http_date(value).ok()?;
+        Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+    }
+
+    /// Rate-limit headers, preferring the standard `RateLimit-*` names over `X-RateLimit-*`
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        let number = |suffix: &str| {
+            self.get(&format!("ratelimit-{suffix}"))
+                .or_else(|| self.get(&format!("x-ratelimit-{suffix}")))
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+        let rate_limit = RateLimit {
+            limit: number("limit"),
+            remaining: number("remaining"),
+            reset: number("reset").map(reset_duration),
+        };
+        (rate_limit != RateLimit::default()).then_some(rate_limit)
+    }
+
+    fn http_date(&self, name: &str) -> Option<SystemTime> {
+        httpdate::parse_http_date(self.get(name)?.trim()).ok()
+    }
+}
+
+// Reset headers carry either delta-seconds or, for some X-RateLimit-Reset implementations, a
+// Unix timestamp in seconds or milliseconds. Deltas of more than ~30 years are taken as the
+// latter.
+fn reset_duration(value: u64) -> Duration {
+    const EPOCH_SECONDS_FROM: u64 = 1_000_000_000;
+    const EPOCH_MILLIS_FROM: u64 = 1_000_000_000_000;
+    let at = match value {
+        v if v >= EPOCH_MILLIS_FROM => UNIX_EPOCH + Duration::from_millis(v),
+        v if v >= EPOCH_SECONDS_FROM => UNIX_EPOCH + Duration::from_secs(v),
+        v => return Duration::from_secs(v),
+    };
+    at.duration_since(SystemTime::now()).unwrap_or_default()
+}
+
+fn entry(name: String, value: String) -> HttpResult<HeaderEntry> {
+    let header_name =
+        HeaderName::from_bytes(name.as_bytes()).map_err(|e| HttpError::InvalidHeader {
+            name: name.clone(),
+            reason: e.to_string(),
+        })?;
+    HeaderValue::from_str(&value).map_err(|e| HttpError::InvalidHeader {
+        name: name.clone(),
+        reason: e.to_string(),
+    })?;
+    let name = header_name.as_str().to_string();
+    let sensitive = DEFAULT_SENSITIVE_HEADERS.contains(&name.as_str());
+    Ok(HeaderEntry {
+        name,
+        value,
+        sensitive,
+    })
+}
+
+impl fmt::Debug for HttpHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.redacted().iter()).finish()
     }
 }
 
-impl From<&HttpHeaders> for reqwest::header::HeaderMap {
-    fn from(headers: &HttpHeaders) -> Self {
-        let mut map = reqwest::header::HeaderMap::new();
-        for (k, v) in &headers.0 {
-            if let Ok(header_name) = reqwest::header::HeaderName::from_bytes(k.as_bytes()) {
-                if let Ok(header_value) = reqwest::header::HeaderValue::from_str(v) {
-                    map.insert(header_name, header_value);
-                }
+impl TryFrom<Vec<HeaderEntry>> for HttpHeaders {
+    type Error = HttpError;
+
+    fn try_from(entries: Vec<HeaderEntry>) -> HttpResult<Self> {
+        let mut headers = HttpHeaders::new();
+        for e in entries {
+            headers.append(e.name.clone(), e.value)?;
+            if e.sensitive {
+                headers.mark_sensitive(&e.name);
             }
         }
-        map
+        Ok(headers)
+    }
+}
+
+impl From<HttpHeaders> for Vec<HeaderEntry> {
+    fn from(headers: HttpHeaders) -> Self {
+        headers.entries
+    }
+}
+
+/// Repeated headers keep every value; values that aren't valid UTF-8 are decoded lossily.
+impl From<&HeaderMap> for HttpHeaders {
+    fn from(map: &HeaderMap) -> Self {
+        let entries = map
+            .iter()
+            .map(|(name, value)| HeaderEntry {
+                name: name.as_str().to_string(),
+                value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                sensitive: value.is_sensitive()
+                    || DEFAULT_SENSITIVE_HEADERS.contains(&name.as_str()),
+            })
+            .collect();
+        HttpHeaders {
+            entries,
+            sensitive_names: BTreeSet::new(),
+        }
+    }
+}
+
+impl From<HeaderMap> for HttpHeaders {
+    fn from(map: HeaderMap) -> Self {
+        HttpHeaders::from(&map)
+    }
+}
+
+/// Sensitive headers are marked with [`HeaderValue::set_sensitive`]
+impl TryFrom<&HttpHeaders> for HeaderMap {
+    type Error = HttpError;
+
+    fn try_from(headers: &HttpHeaders) -> HttpResult<Self> {
+        let mut map = HeaderMap::with_capacity(headers.len());
+        for entry in &headers.entries {
+            let invalid = |reason: String| HttpError::InvalidHeader {
+                name: entry.name.clone(),
+                reason,
+            };
+            let name = HeaderName::from_bytes(entry.name.as_bytes())
+                .map_err(|e| invalid(e.to_string()))?;
+            let mut value =
+                HeaderValue::from_str(&entry.value).map_err(|e| invalid(e.to_string()))?;
+            value.set_sensitive(entry.sensitive);
+            map.append(name, value);
+        }
+        Ok(map)
+    }
+}
+
+impl TryFrom<HttpHeaders> for HeaderMap {
+    type Error = HttpError;
+
+    fn try_from(headers: HttpHeaders) -> HttpResult<Self> {
+        HeaderMap::try_from(&headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_case_insensitive_and_values_repeat() {
+        let mut headers = HttpHeaders::new();
+        headers.append("Accept", "a").unwrap();
+        headers.append("ACCEPT", "b").unwrap();
+        assert_eq!(headers.get_all("accept").collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(headers.get_joined("Accept").as_deref(), Some("a, b"));
+        headers.insert("accept", "c").unwrap();
+        assert_eq!(headers.get_all("accept").collect::<Vec<_>>(), ["c"]);
+        assert!(matches!(
+            headers.insert("bad name", "x"),
+            Err(HttpError::InvalidHeader { .. })
+        ));
+        assert!(headers.insert("x-ok", "line\nbreak").is_err());
+    }
+
+    #[test]
+    fn marked_names_stay_sensitive_for_later_values() {
+        let mut headers = HttpHeaders::new();
+        headers.mark_sensitive("X-Api-Token");
+        headers.insert("x-api-token", "one").unwrap();
+        headers.append("X-API-TOKEN", "two").unwrap();
+        headers.remove("x-api-token");
+        headers.insert("x-api-token", "three").unwrap();
+        assert!(headers.is_sensitive("x-api-token"));
+        assert_eq!(headers.redacted().get("x-api-token"), Some(REDACTED));
+        assert!(!format!("{headers:?}").contains("three"));
+        let map = HeaderMap::try_from(&headers).unwrap();
+        assert!(map.get("x-api-token").unwrap().is_sensitive());
+    }
+
+    #[test]
+    fn extend_keeps_sensitivity_from_both_sides() {
+        let mut receiver = HttpHeaders::new();
+        receiver.mark_sensitive("x-secret");
+        let mut other = HttpHeaders::new();
+        other.insert("x-secret", "value").unwrap();
+        other.insert_sensitive("x-other", "value").unwrap();
+        receiver.extend(other);
+        assert!(receiver.is_sensitive("x-secret"));
+        assert!(receiver.is_sensitive("x-other"));
+        assert_eq!(receiver.redacted().get("x-secret"), Some(REDACTED));
+        receiver.insert("x-other", "new").unwrap();
+        assert_eq!(receiver.redacted().get("x-other"), Some(REDACTED));
+    }
+
+    #[test]
+    fn rate_limit_reset_accepts_deltas_and_timestamps() {
+        let mut headers = HttpHeaders::new();
+        headers.insert("x-ratelimit-remaining", "3").unwrap();
+        headers.insert("x-ratelimit-reset", "30").unwrap();
+        let rate_limit = headers.rate_limit().unwrap();
+        assert_eq!(rate_limit.remaining, Some(3));
+        assert_eq!(rate_limit.reset, Some(Duration::from_secs(30)));
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        for reset in [
+            (now.as_secs() + 60).to_string(),
+            (now.as_millis() + 60_000).to_string(),
+        ] {
+            headers.insert("x-ratelimit-reset", reset).unwrap();
+            let reset = headers.rate_limit().unwrap().reset.unwrap();
+            assert!(reset > Duration::from_secs(55) && reset <= Duration::from_secs(60));
+        }
+        headers.insert("x-ratelimit-reset", "1000000000").unwrap();
+        assert_eq!(headers.rate_limit().unwrap().reset, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn typed_getters_parse_common_headers() {
+        let mut headers = HttpHeaders::new();
+        headers
+            .insert("content-type", "Application/JSON; charset=utf-8")
+            .unwrap();
+        headers.insert("content-length", "12").unwrap();
+        headers.insert("retry-after", "7").unwrap();
+        headers
+            .insert("date", "Sun, 06 Nov 1994 08:49:37 GMT")
+            .unwrap();
+        assert_eq!(headers.media_type().as_deref(), Some("application/json"));
+        assert_eq!(headers.content_length(), Some(12));
+        assert_eq!(headers.retry_after(), Some(Duration::from_secs(7)));
+        assert_eq!(
+            headers.date(),
+            Some(UNIX_EPOCH + Duration::from_secs(784111777))
+        );
     }
 }
@@ -13,7 +13,11 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-#[derive(Debug, Clone, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum HttpMethod {
     Get,
     Post,
@@ -26,18 +30,72 @@ pub enum HttpMethod {
     Connect,
 }
 
-impl From<HttpMethod> for reqwest::Method {
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Options => "OPTIONS",
+            HttpMethod::Trace => "TRACE",
+            HttpMethod::Connect => "CONNECT",
+        }
+    }
+
+    /// Whether sending the request twice has the same effect as sending it once (RFC 9110)
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            HttpMethod::Get
+                | HttpMethod::Head
+                | HttpMethod::Options
+                | HttpMethod::Trace
+                | HttpMethod::Put
+                | HttpMethod::Delete
+        )
+    }
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<HttpMethod> for http::Method {
     fn from(method: HttpMethod) -> Self {
         match method {
-            HttpMethod::Get => reqwest::Method::GET,
-            HttpMethod::Post => reqwest::Method::POST,
-            HttpMethod::Put => reqwest::Method::PUT,
-            HttpMethod::Delete => reqwest::Method::DELETE,
-            HttpMethod::Patch => reqwest::Method::PATCH,
-            HttpMethod::Head => reqwest::Method::HEAD,
-            HttpMethod::Options => reqwest::Method::OPTIONS,
-            HttpMethod::Trace => reqwest::Method::TRACE,
-            HttpMethod::Connect => reqwest::Method::CONNECT,
+            HttpMethod::Get => http::Method::GET,
+            HttpMethod::Post => http::Method::POST,
+            HttpMethod::Put => http::Method::PUT,
+            HttpMethod::Delete => http::Method::DELETE,
+            HttpMethod::Patch => http::Method::PATCH,
+            HttpMethod::Head => http::Method::HEAD,
+            HttpMethod::Options => http::Method::OPTIONS,
+            HttpMethod::Trace => http::Method::TRACE,
+            HttpMethod::Connect => http::Method::CONNECT,
+        }
+    }
+}
+
+impl TryFrom<&http::Method> for HttpMethod {
+    type Error = String;
+
+    fn try_from(method: &http::Method) -> Result<Self, Self::Error> {
+        match *method {
+            http::Method::GET => Ok(HttpMethod::Get),
+            http::Method::POST => Ok(HttpMethod::Post),
+            http::Method::PUT => Ok(HttpMethod::Put),
+            http::Method::DELETE => Ok(HttpMethod::Delete),
+            http::Method::PATCH => Ok(HttpMethod::Patch),
+            http::Method::HEAD => Ok(HttpMethod::Head),
+            http::Method::OPTIONS => Ok(HttpMethod::Options),
+            http::Method::TRACE => Ok(HttpMethod::Trace),
+            http::Method::CONNECT => Ok(HttpMethod::Connect),
+            _ => Err(format!("Unsupported HTTP method: {}", method)),
         }
     }
 }
@@ -13,38 +13,121 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::credentials::Credentials;
+#[cfg(feature = "reqwest-compat")]
+use crate::error::{HttpError, HttpResult};
+use crate::extensions::Extensions;
+use crate::http_headers::HttpHeaders;
 use crate::http_method::HttpMethod;
+use crate::http_url::HttpUrl;
+use crate::idempotency::IdempotencyStrategy;
+use crate::multipart::Multipart;
+use base64::Engine;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::time::Duration;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RetryPolicy {
     pub max_attempts: u32,
     pub backoff_millis: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "content")]
+pub enum RequestBody {
+    #[default]
+    Empty,
+    Json(Value),
+    /// Raw bytes; set a `Content-Type` header to describe them
+    Bytes(Bytes),
+    /// Streamed when the request is sent, so it can't be serialized
+    #[serde(skip)]
+    Multipart(Multipart),
+}
+
+/// An HTTP request as a plain value: method, route, query, headers and body.
+///
+/// The client resolves the route against its base URL and converts the request to its
+/// transport only when it is sent, so requests can be built, cloned, compared and
+/// serialized without a client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpRequest {
-    inner: reqwest::Request,
-    pub retry_policy: Option<RetryPolicy>,
+    pub method: HttpMethod,
+    /// Route relative to the client's base URL, or an absolute URL
     pub path: Option<String>,
+    /// The resolved URL without query parameters, set by the client before pre-request
+    /// interceptors run
+    pub url: Option<HttpUrl>,
     pub query_params: Option<HashMap<String, String>>,
-    pub json_body: Option<Value>,
+    pub headers: HttpHeaders,
+    pub body: RequestBody,
+    pub retry_policy: Option<RetryPolicy>,
+    pub timeout: Option<Duration>,
+    pub deadline: Option<Duration>,
+    retryable: bool,
+    idempotency: Option<IdempotencyStrategy>,
+    idempotency_key: Option<String>,
+    streaming_response: bool,
+    #[serde(skip)]
+    extensions: Extensions,
+}
+
+// Extensions hold arbitrary in-process values with no equality of their own, so they are
+// left out of the comparison
+impl PartialEq for HttpRequest {
+    fn eq(&self, other: &Self) -> bool {
+        let HttpRequest {
+            method,
+            path,
+            url,
+            query_params,
+            headers,
+            body,
+            retry_policy,
+            timeout,
+            deadline,
+            retryable,
+            idempotency,
+            idempotency_key,
+            streaming_response,
+            extensions: _,
+        } = self;
+        *method == other.method
+            && *path == other.path
+            && *url == other.url
+            && *query_params == other.query_params
+            && *headers == other.headers
+            && *body == other.body
+            && *retry_policy == other.retry_policy
+            && *timeout == other.timeout
+            && *deadline == other.deadline
+            && *retryable == other.retryable
+            && *idempotency == other.idempotency
+            && *idempotency_key == other.idempotency_key
+            && *streaming_response == other.streaming_response
+    }
 }
 
 impl HttpRequest {
-    // Builder methods for creating requests without reqwest
     pub fn new(method: HttpMethod, path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        // Create a placeholder URL that will be replaced by the client with the base URL
-        let placeholder_url = reqwest::Url::parse("http://placeholder.com")?;
-        let reqwest_method: reqwest::Method = method.into();
-        let inner = reqwest::Request::new(reqwest_method, placeholder_url);
         Ok(Self {
-            inner,
-            retry_policy: None,
+            method,
             path: Some(path.to_string()),
+            url: None,
             query_params: None,
-            json_body: None,
+            headers: HttpHeaders::new(),
+            body: RequestBody::Empty,
+            retry_policy: None,
+            timeout: None,
+            deadline: None,
+            retryable: true,
+            idempotency: None,
+            idempotency_key: None,
+            streaming_response: false,
+            extensions: Extensions::new(),
         })
     }
 
@@ -58,47 +141,434 @@ impl HttpRequest {
         self
     }
 
+    /// Set a header from literal values.
+    ///
+    /// # Panics
+    ///
+    /// If the name or value is not a valid header; use [`HttpRequest::add_header`] for values that aren't known
+    /// to be valid.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        if let Err(e) = self.headers.insert(name, value) {
+            panic!("{e}");
+        }
+        self
+    }
+
+    /// Send a JSON body. Put prices and quantities in as [`crate::Amount`], which becomes a
+    /// JSON string; floats in `body` are sent as `f64`.
     pub fn with_json_body(mut self, body: Value) -> Self {
-        self.json_body = Some(body);
+        self.body = RequestBody::Json(body);
         self
     }
 
+    /// Send a typed value as the JSON body; its [`crate::Amount`] fields are sent as strings
+    pub fn with_json<T: Serialize>(self, body: &T) -> Result<Self, serde_json::Error> {
+        Ok(self.with_json_body(serde_json::to_value(body)?))
+    }
+
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = RequestBody::Bytes(body.into());
+        self
+    }
+
+    /// Send a `multipart/form-data` body. Requests with one-shot reader parts are marked
+    /// non-retryable since their body can't be rebuilt for another attempt.
+    pub fn with_multipart(mut self, multipart: Multipart) -> Self {
+        if !multipart.is_replayable() {
+            self.retryable = false;
+        }
+        self.body = RequestBody::Multipart(multipart);
+        self
+    }
+
+    pub fn json_body(&self) -> Option<&Value> {
+        match &self.body {
+            RequestBody::Json(json) => Some(json),
+            _ => None,
+        }
+    }
+
+    pub fn multipart(&self) -> Option<&Multipart> {
+        match &self.body {
+            RequestBody::Multipart(multipart) => Some(multipart),
+            _ => None,
+        }
+    }
+
+    /// Time limit for each attempt; an attempt that runs over is retried if the policy allows
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Time limit for the whole request, spanning every attempt and the backoff between them
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Mark the request as safe (or not) to send more than once
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    /// Leave the response body on the connection so it can be streamed, instead of reading
+    /// it into memory before the response is returned
+    pub fn with_streaming_response(mut self, streaming: bool) -> Self {
+        self.streaming_response = streaming;
+        self
+    }
+
+    pub fn is_streaming_response(&self) -> bool {
+        self.streaming_response
+    }
+
+    /// Attach a value for interceptors and middleware; it is copied into the response
+    pub fn with_extension<T: Clone + Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.extensions.insert(value);
+        self
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Send an idempotency key with this request, generating one if none was set.
+    /// Overrides the client's default strategy.
+    pub fn with_idempotency(mut self, strategy: IdempotencyStrategy) -> Self {
+        self.idempotency = Some(strategy);
+        self
+    }
+
+    /// Use a caller-chosen idempotency key, sent in the `Idempotency-Key` header unless
+    /// another strategy is set
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        if self.idempotency.is_none() {
+            self.idempotency = Some(IdempotencyStrategy::header());
+        }
+        self
+    }
+
+    pub fn idempotency(&self) -> Option<&IdempotencyStrategy> {
+        self.idempotency.as_ref()
+    }
+
+    /// The key reused by every attempt of this request, once one has been assigned
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    pub(crate) fn set_idempotency_key(&mut self, key: String) {
+        self.idempotency_key = Some(key);
+    }
+
+    /// The serialized body that is sent on the wire, if it is known up front
+    pub fn body_bytes(&self) -> Option<Vec<u8>> {
+        match &self.body {
+            RequestBody::Json(json) => Some(json.to_string().into_bytes()),
+            RequestBody::Bytes(bytes) => Some(bytes.to_vec()),
+            RequestBody::Empty | RequestBody::Multipart(_) => None,
+        }
+    }
+
+    /// The method, path and body a request signer should cover.
+    ///
+    /// The path is the one sent on the wire: the resolved URL's path, including any base
+    /// URL prefix, followed by the query string. Call this from a pre-request interceptor,
+    /// after the client has resolved the URL; before that the route is used as given.
+    /// Multipart bodies are generated while the request is sent, so their credentials have
+    /// no body and [`Credentials::unsigned_body`] set.
+    pub fn signing_credentials(&self) -> Credentials {
+        let path = match &self.url {
+            Some(url) => {
+                let url = self.full_url(url);
+                match url.0.query() {
+                    Some(query) => format!("{}?{}", url.0.path(), query),
+                    None => url.0.path().to_string(),
+                }
+            }
+            None => self.display_url(),
+        };
+        let mut credentials = Credentials::new(self.method.clone(), path, self.body_bytes());
+        credentials.unsigned_body = matches!(self.body, RequestBody::Multipart(_));
+        credentials
+    }
+
     pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
         self.retry_policy = Some(policy);
         self
     }
 
+    /// Copy the request so it can be sent again independently, e.g. as a hedge.
+    ///
+    /// Returns `None` when the body can only be sent once; a plain `clone` of such a
+    /// request shares the one-shot body with the original.
+    pub fn try_clone(&self) -> Option<HttpRequest> {
+        if self.multipart().is_some_and(|m| !m.is_replayable()) {
+            return None;
+        }
+        Some(self.clone())
+    }
+
     // Header manipulation methods
     pub fn add_header(
         &mut self,
         name: &str,
         value: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let header_name = reqwest::header::HeaderName::from_bytes(name.as_bytes())?;
-        let header_value = reqwest::header::HeaderValue::from_str(value)?;
-        self.inner.headers_mut().insert(header_name, header_value);
+        self.headers.insert(name, value)?;
         Ok(())
     }
 
     pub fn get_method(&self) -> &str {
-        self.inner.method().as_str()
+        self.method.as_str()
     }
 
+    /// The path of the resolved URL, or `/` before the client has resolved it
     pub fn get_url_path(&self) -> &str {
-        self.inner.url().path()
+        self.url.as_ref().map_or("/", |url| url.0.path())
+    }
+
+    pub fn set_url(&mut self, url: HttpUrl) {
+        self.url = Some(url);
+    }
+
+    /// Build the equivalent `reqwest::Request`. The URL must already be resolved, either by
+    /// [`HttpRequest::set_url`] or by an absolute path.
+    #[cfg(feature = "reqwest-compat")]
+    pub fn to_reqwest(&self) -> HttpResult<reqwest::Request> {
+        let url = match (&self.url, &self.path) {
+            (Some(url), _) => url.clone(),
+            (None, Some(path)) if HttpUrl::is_absolute(path) => HttpUrl::parse(path)
+                .map_err(|e| HttpError::Custom(format!("Invalid request URL '{path}': {e}")))?,
+            _ => {
+                return Err(HttpError::Custom(
+                    "Request URL has not been resolved".to_string(),
+                ))
+            }
+        };
+        crate::reqwest_transport::to_reqwest_request(self, self.full_url(&url))
+    }
+
+    /// Convert into a `reqwest::Request`; see [`HttpRequest::to_reqwest`]
+    #[cfg(feature = "reqwest-compat")]
+    pub fn into_inner(self) -> HttpResult<reqwest::Request> {
+        self.to_reqwest()
+    }
+
+    /// A `curl` command that reproduces the request, with sensitive header values such as
+    /// `Authorization` masked.
+    ///
+    /// The URL includes the query parameters. It is only absolute once the client has
+    /// resolved it (e.g. in a pre-request interceptor) or when the path is an absolute URL.
+    pub fn to_curl(&self) -> String {
+        self.curl_command(true)
     }
 
-    // Accessor methods
-    pub fn as_reqwest(&self) -> &reqwest::Request {
-        &self.inner
+    /// Like [`HttpRequest::to_curl`], but with every header value shown
+    pub fn to_curl_unredacted(&self) -> String {
+        self.curl_command(false)
     }
-    pub fn as_mut_reqwest(&mut self) -> &mut reqwest::Request {
-        &mut self.inner
+
+    fn curl_command(&self, redact: bool) -> String {
+        // `-X HEAD` would make curl wait for a body that never comes
+        let method = match self.method {
+            HttpMethod::Get => String::new(),
+            HttpMethod::Head => "--head ".to_string(),
+            _ => format!("-X {} ", self.method),
+        };
+        let mut args = vec![format!("curl {method}{}", shell_quote(&self.display_url()))];
+        let headers = if redact {
+            self.headers.redacted()
+        } else {
+            self.headers.clone()
+        };
+        for (name, value) in headers.iter() {
+            args.push(format!("-H {}", shell_quote(&format!("{name}: {value}"))));
+        }
+        let mut stdin = None;
+        match &self.body {
+            RequestBody::Empty => {}
+            RequestBody::Json(json) => {
+                if !self.headers.contains("content-type") {
+                    args.push(format!(
+                        "-H {}",
+                        shell_quote("content-type: application/json")
+                    ));
+                }
+                args.push(format!("--data-raw {}", shell_quote(&json.to_string())));
+            }
+            RequestBody::Bytes(bytes) => match std::str::from_utf8(bytes) {
+                Ok(text) => args.push(format!("--data-raw {}", shell_quote(text))),
+                // Binary bodies are piped in as base64 so the command stays copyable
+                Err(_) => {
+                    stdin = Some(base64::engine::general_purpose::STANDARD.encode(bytes));
+                    args.push("--data-binary @-".to_string());
+                }
+            },
+            RequestBody::Multipart(multipart) => {
+                for (option, value) in multipart.curl_forms() {
+                    args.push(format!("{option} {}", shell_quote(&value)));
+                }
+            }
+        }
+        let command = args.join(" \\\n  ");
+        match stdin {
+            Some(encoded) => format!("printf '%s' '{encoded}' | base64 -d | {command}"),
+            None => command,
+        }
+    }
+
+    // The resolved URL with query parameters, or the route when it isn't resolved yet
+    pub(crate) fn display_url(&self) -> String {
+        let url = match (&self.url, &self.path) {
+            (Some(url), _) => Some(url.clone()),
+            (None, Some(path)) if HttpUrl::is_absolute(path) => HttpUrl::parse(path).ok(),
+            _ => None,
+        };
+        if let Some(url) = url {
+            return self.full_url(&url).to_string();
+        }
+        let mut route = self.path.clone().unwrap_or_else(|| "/".to_string());
+        if let Some(params) = self.sorted_query_params().filter(|p| !p.is_empty()) {
+            let query = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(params)
+                .finish();
+            route.push(if route.contains('?') { '&' } else { '?' });
+            route.push_str(&query);
+        }
+        route
+    }
+
+    /// The resolved URL with the query parameters appended
+    pub(crate) fn full_url(&self, url: &HttpUrl) -> HttpUrl {
+        let mut url = url.0.clone();
+        if let Some(params) = self.sorted_query_params() {
+            let mut pairs = url.query_pairs_mut();
+            for (k, v) in params {
+                pairs.append_pair(k, v);
+            }
+        }
+        HttpUrl(url)
+    }
+
+    // Query parameters in a stable order, so the signed and sent query strings match
+    fn sorted_query_params(&self) -> Option<Vec<(&String, &String)>> {
+        let mut params: Vec<_> = self.query_params.as_ref()?.iter().collect();
+        params.sort();
+        Some(params)
+    }
+}
+
+// Quote a value for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Build a request from a `reqwest::Request` with a buffered body
+#[cfg(feature = "reqwest-compat")]
+impl TryFrom<reqwest::Request> for HttpRequest {
+    type Error = HttpError;
+
+    fn try_from(request: reqwest::Request) -> HttpResult<Self> {
+        let method = HttpMethod::try_from(request.method()).map_err(HttpError::Custom)?;
+        let mut http_request = HttpRequest::new(method, request.url().as_str())
+            .map_err(|e| HttpError::Custom(e.to_string()))?;
+        http_request.url = Some(HttpUrl(request.url().clone()));
+        http_request.headers = HttpHeaders::from(request.headers());
+        if let Some(body) = request.body() {
+            let bytes = body.as_bytes().ok_or_else(|| {
+                HttpError::Custom("Streaming request bodies cannot be converted".to_string())
+            })?;
+            http_request.body = RequestBody::Bytes(Bytes::copy_from_slice(bytes));
+        }
+        Ok(http_request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multipart::Multipart;
+
+    fn resolved(path: &str, base: &str) -> HttpRequest {
+        let mut request = HttpRequest::new(HttpMethod::Get, path).unwrap();
+        let base = HttpUrl::parse(base).unwrap();
+        request.set_url(base.resolve(path, false).unwrap());
+        request
+    }
+
+    #[test]
+    fn signs_the_wire_path_with_base_prefix_and_query() {
+        let mut request = resolved("/portfolios", "https://api.example.com/v1");
+        request.query_params = Some(HashMap::from([
+            ("limit".to_string(), "10".to_string()),
+            ("cursor".to_string(), "a b".to_string()),
+        ]));
+        let credentials = request.signing_credentials();
+        assert_eq!(credentials.path, "/v1/portfolios?cursor=a+b&limit=10");
+        assert_eq!(
+            request
+                .full_url(request.url.as_ref().unwrap())
+                .0
+                .query()
+                .unwrap(),
+            "cursor=a+b&limit=10"
+        );
+    }
+
+    #[test]
+    fn unresolved_requests_sign_the_route() {
+        let request = HttpRequest::new(HttpMethod::Post, "/orders")
+            .unwrap()
+            .with_json_body(serde_json::json!({"size": "1"}));
+        let credentials = request.signing_credentials();
+        assert_eq!(credentials.path, "/orders");
+        assert_eq!(credentials.body.unwrap(), br#"{"size":"1"}"#);
+        assert!(!credentials.unsigned_body);
     }
-    pub fn into_inner(self) -> reqwest::Request {
-        self.inner
+
+    #[test]
+    fn multipart_bodies_are_flagged_unsigned() {
+        let request = resolved("/documents", "https://api.example.com/v1")
+            .with_multipart(Multipart::new().with_text("a", "b"));
+        let credentials = request.signing_credentials();
+        assert!(credentials.body.is_none());
+        assert!(credentials.unsigned_body);
     }
-    pub fn set_url(&mut self, url: reqwest::Url) {
-        *self.inner.url_mut() = url;
+
+    #[test]
+    fn curl_masks_auth_and_uses_head_for_head_requests() {
+        let mut request = resolved("/orders", "https://api.example.com/v1")
+            .with_header("CB-ACCESS-SIGN", "secret")
+            .with_header("x-trace", "it's");
+        request.method = HttpMethod::Head;
+        let curl = request.to_curl();
+        assert!(
+            curl.starts_with("curl --head 'https://api.example.com/v1/orders'"),
+            "{curl}"
+        );
+        assert!(!curl.contains("-X HEAD"));
+        assert!(!curl.contains("secret") && curl.contains("cb-access-sign: ***"));
+        assert!(curl.contains(r"-H 'x-trace: it'\''s'"), "{curl}");
+        assert!(request
+            .to_curl_unredacted()
+            .contains("cb-access-sign: secret"));
+
+        request.method = HttpMethod::Post;
+        request.body = RequestBody::Json(serde_json::json!({"a": 1}));
+        let curl = request.to_curl();
+        assert!(curl.starts_with("curl -X POST "), "{curl}");
+        assert!(curl.contains(r#"--data-raw '{"a":1}'"#), "{curl}");
     }
 }
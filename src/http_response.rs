@@ -13,41 +13,337 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::cache::CacheStatus;
+use crate::download::DownloadOptions;
+use crate::error::{HttpError, HttpResult};
+use crate::extensions::Extensions;
+use crate::http_headers::HttpHeaders;
 use crate::http_status_code::HttpStatusCode;
+use crate::http_url::HttpUrl;
+use crate::json_stream::{self, JsonStreamOptions};
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+type OneShotStream = Arc<Mutex<Option<BoxStream<'static, HttpResult<Bytes>>>>>;
+
+/// A response body decoded by status class, kept alongside the raw bytes it was decoded from.
 #[derive(Debug)]
+pub struct Decoded<T, E> {
+    pub status: HttpStatusCode,
+    pub content_type: Option<String>,
+    pub raw: Bytes,
+    pub result: Result<T, E>,
+}
+
+impl<T, E> Decoded<T, E> {
+    pub fn into_result(self) -> Result<T, E> {
+        self.result
+    }
+}
+
+impl<T: DeserializeOwned, E: DeserializeOwned> Decoded<T, E> {
+    /// Decode the outcome of a client call: successful responses as `T` and error responses
+    /// as `E`.
+    ///
+    /// The client returns 4xx/5xx responses as [`HttpError::Status`], so this is the way to
+    /// get a typed API error from it. Other errors are returned unchanged.
+    pub async fn from_result(result: HttpResult<HttpResponse>) -> HttpResult<Self> {
+        let error = match result {
+            Ok(response) => return response.decode().await,
+            Err(error) => error,
+        };
+        let HttpError::Status {
+            status,
+            headers,
+            body,
+        } = error.root()
+        else {
+            return Err(error);
+        };
+        let content_type = headers.content_type().map(str::to_string);
+        let decoded = decode_body::<E>(*status, content_type.as_deref(), body, "<error response>")?;
+        Ok(Decoded {
+            status: *status,
+            content_type,
+            raw: body.clone(),
+            result: Err(decoded),
+        })
+    }
+}
+
+#[derive(Clone)]
+enum ResponseBody {
+    Buffered(Bytes),
+    /// Read from the connection on demand; clones share the one stream
+    Streaming(OneShotStream),
+}
+
+impl fmt::Debug for ResponseBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseBody::Buffered(bytes) => write!(f, "Buffered({} bytes)", bytes.len()),
+            ResponseBody::Streaming(_) => write!(f, "Streaming"),
+        }
+    }
+}
+
+/// An HTTP response with its body read into memory.
+///
+/// Responses are cheap to clone and can be built directly, e.g. in tests or by a cache.
+/// Requests made with [`crate::http_request::HttpRequest::with_streaming_response`] get a
+/// body that is read from the connection as it is consumed instead.
+#[derive(Debug, Clone)]
 pub struct HttpResponse {
-    inner: reqwest::Response,
+    status: HttpStatusCode,
+    headers: HttpHeaders,
+    body: ResponseBody,
+    url: Option<HttpUrl>,
+    elapsed: Duration,
+    attempts: u32,
+    endpoint: Option<HttpUrl>,
+    idempotency_key: Option<String>,
+    correlation_id: Option<String>,
+    request_id: Option<String>,
+    cache_status: Option<CacheStatus>,
+    request_headers: Option<HttpHeaders>,
+    extensions: Extensions,
 }
 
 impl HttpResponse {
-    pub fn new(inner: reqwest::Response) -> Self {
-        Self { inner }
+    pub fn new(status: HttpStatusCode) -> Self {
+        Self {
+            status,
+            headers: HttpHeaders::new(),
+            body: ResponseBody::Buffered(Bytes::new()),
+            url: None,
+            elapsed: Duration::ZERO,
+            attempts: 1,
+            endpoint: None,
+            idempotency_key: None,
+            correlation_id: None,
+            request_id: None,
+            cache_status: None,
+            request_headers: None,
+            extensions: Extensions::new(),
+        }
+    }
+
+    pub fn from_parts(
+        status: HttpStatusCode,
+        headers: HttpHeaders,
+        body: impl Into<Bytes>,
+    ) -> Self {
+        Self::new(status).with_headers(headers).with_body(body)
+    }
+
+    /// A response whose body is read from `body` as the caller consumes it
+    pub fn streaming(
+        status: HttpStatusCode,
+        headers: HttpHeaders,
+        body: impl Stream<Item = HttpResult<Bytes>> + Send + 'static,
+    ) -> Self {
+        let mut response = Self::new(status).with_headers(headers);
+        response.body = ResponseBody::Streaming(Arc::new(Mutex::new(Some(body.boxed()))));
+        response
+    }
+
+    pub fn with_headers(mut self, headers: HttpHeaders) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Set a header from literal values.
+    ///
+    /// # Panics
+    ///
+    /// If the name or value is not a valid header; use [`HttpResponse::headers_mut`] for values that aren't known
+    /// to be valid.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        if let Err(e) = self.headers.insert(name, value) {
+            panic!("{e}");
+        }
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = ResponseBody::Buffered(body.into());
+        self
+    }
+
+    pub fn with_url(mut self, url: HttpUrl) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed = elapsed;
+        self
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    pub(crate) fn with_endpoint(mut self, endpoint: HttpUrl) -> Self {
+        self.endpoint = Some(endpoint);
+        self
     }
-    pub fn as_reqwest(&self) -> &reqwest::Response {
-        &self.inner
+
+    pub(crate) fn with_idempotency_key(mut self, key: String) -> Self {
+        self.idempotency_key = Some(key);
+        self
+    }
+
+    pub(crate) fn with_correlation_id(mut self, id: String) -> Self {
+        self.correlation_id = Some(id);
+        self
+    }
+
+    pub(crate) fn with_request_id(mut self, id: Option<String>) -> Self {
+        self.request_id = id;
+        self
     }
-    pub fn as_mut_reqwest(&mut self) -> &mut reqwest::Response {
-        &mut self.inner
+
+    pub(crate) fn with_cache_status(mut self, status: CacheStatus) -> Self {
+        self.cache_status = Some(status);
+        self
     }
-    pub fn into_inner(self) -> reqwest::Response {
-        self.inner
+
+    pub(crate) fn with_request_headers(mut self, headers: Option<HttpHeaders>) -> Self {
+        self.request_headers = headers;
+        self
+    }
+
+    pub fn with_extension<T: Clone + Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.extensions.insert(value);
+        self
+    }
+
+    /// Replace the extensions, e.g. with those of the request that produced the response
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
     }
 
     /// Get the HTTP status code from the response
     pub fn status(&self) -> HttpStatusCode {
-        self.inner.status().into()
+        self.status
+    }
+
+    pub fn headers(&self) -> &HttpHeaders {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HttpHeaders {
+        &mut self.headers
+    }
+
+    /// Look up a header value, ignoring the case of the name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// The final URL, after redirects
+    pub fn url(&self) -> Option<&HttpUrl> {
+        self.url.as_ref()
+    }
+
+    /// Time from sending the first attempt to receiving this response
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// How many attempts were made, including the one that produced this response
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// The body, unless it is still being streamed from the connection
+    pub fn body(&self) -> Option<&Bytes> {
+        match &self.body {
+            ResponseBody::Buffered(bytes) => Some(bytes),
+            ResponseBody::Streaming(_) => None,
+        }
+    }
+
+    pub fn is_buffered(&self) -> bool {
+        matches!(self.body, ResponseBody::Buffered(_))
+    }
+
+    /// The body length if known, from the buffered body or the `Content-Length` header
+    pub fn content_length(&self) -> Option<u64> {
+        match &self.body {
+            ResponseBody::Buffered(bytes) => Some(bytes.len() as u64),
+            ResponseBody::Streaming(_) => self.headers.content_length(),
+        }
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.content_type()
+    }
+
+    /// The idempotency key the request was sent with
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    /// The correlation id the request was sent with
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// The server's id for the request, from the client's request-id headers
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Whether the response came from a response cache, when one is in use
+    pub fn cache_status(&self) -> Option<CacheStatus> {
+        self.cache_status
+    }
+
+    /// The headers the request was sent with, including default and signing headers added
+    /// by the client
+    pub fn request_headers(&self) -> Option<&HttpHeaders> {
+        self.request_headers.as_ref()
+    }
+
+    /// Values attached by the request, interceptors and middleware
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// The configured endpoint that served this response, when the client has several
+    pub fn endpoint(&self) -> Option<&HttpUrl> {
+        self.endpoint.as_ref()
+    }
+
+    /// Read a streaming body into memory; buffered responses are returned as they are
+    pub async fn buffer(mut self) -> HttpResult<HttpResponse> {
+        if let ResponseBody::Streaming(_) = self.body {
+            let chunks: Vec<Bytes> = self.clone().bytes_stream().try_collect().await?;
+            self.body = ResponseBody::Buffered(Bytes::from(chunks.concat()));
+        }
+        Ok(self)
     }
 
     pub async fn json<T: serde::de::DeserializeOwned>(self) -> crate::error::HttpResult<T> {
-        let status = self.inner.status();
-        let url = self.inner.url().clone();
+        let status = self.status;
+        let url = self.url_display();
 
         // Get the response body as bytes first
-        let bytes = match self.inner.bytes().await {
-            Ok(bytes) => bytes,
-            Err(e) => return Err(crate::error::HttpError::Reqwest(e)),
-        };
+        let bytes = self.json_bytes().await?;
 
         // Try to deserialize from the bytes
         match serde_json::from_slice::<T>(&bytes) {
@@ -69,17 +365,264 @@ impl HttpResponse {
         }
     }
 
+    /// Decode the body as `T` for non-error statuses and as `E` for 4xx/5xx statuses.
+    ///
+    /// Responses returned by the client are never 4xx/5xx; use [`Decoded::from_result`] on
+    /// the client's result to decode error responses too.
+    ///
+    /// Empty bodies (204, 205, 304 or responses to HEAD) decode from `null`, so `()` and
+    /// `Option<_>` can be used for endpoints that return no content. A body with a
+    /// non-JSON `Content-Type` is rejected before parsing.
+    pub async fn decode<T, E>(self) -> HttpResult<Decoded<T, E>>
+    where
+        T: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let status = self.status();
+        let url = self.url_display();
+        let content_type = self.content_type().map(str::to_string);
+        let raw = self.json_bytes().await?;
+
+        let result = if status.is_error() {
+            Err(decode_body::<E>(
+                status,
+                content_type.as_deref(),
+                &raw,
+                &url,
+            )?)
+        } else {
+            Ok(decode_body::<T>(
+                status,
+                content_type.as_deref(),
+                &raw,
+                &url,
+            )?)
+        };
+
+        Ok(Decoded {
+            status,
+            content_type,
+            raw,
+            result,
+        })
+    }
+
+    /// Parse the body into a `serde_json::Value`.
+    ///
+    /// Numbers are only kept as their literal text with the `arbitrary-precision` feature;
+    /// decode amounts with [`HttpResponse::json`] into types holding [`crate::Amount`] to
+    /// avoid `f64` without it.
+    pub async fn json_value(self) -> HttpResult<serde_json::Value> {
+        self.json().await
+    }
+
     pub async fn json_bytes(self) -> crate::error::HttpResult<bytes::Bytes> {
-        self.inner
-            .bytes()
+        match self.buffer().await?.body {
+            ResponseBody::Buffered(bytes) => Ok(bytes),
+            ResponseBody::Streaming(_) => unreachable!("buffer() always buffers the body"),
+        }
+    }
+
+    /// Stream the body as it arrives instead of buffering it in memory
+    pub fn bytes_stream(self) -> impl Stream<Item = HttpResult<Bytes>> + Send {
+        match self.body {
+            ResponseBody::Buffered(bytes) => {
+                stream::once(futures_util::future::ready(Ok(bytes))).boxed()
+            }
+            ResponseBody::Streaming(shared) => {
+                let taken = shared.lock().ok().and_then(|mut body| body.take());
+                taken.unwrap_or_else(|| {
+                    stream::once(futures_util::future::ready(Err(HttpError::Custom(
+                        "Streaming response body has already been consumed".to_string(),
+                    ))))
+                    .boxed()
+                })
+            }
+        }
+    }
+
+    /// Decode a newline-delimited JSON body item by item as it arrives
+    pub fn ndjson_stream<T: DeserializeOwned>(self) -> impl Stream<Item = HttpResult<T>> {
+        json_stream::ndjson(self.bytes_stream(), JsonStreamOptions::default())
+    }
+
+    /// Decode a top-level JSON array body element by element as it arrives
+    pub fn json_array_stream<T: DeserializeOwned>(self) -> impl Stream<Item = HttpResult<T>> {
+        json_stream::json_array(self.bytes_stream(), JsonStreamOptions::default())
+    }
+
+    /// Write the body to `path` atomically, verifying a `Repr-Digest`/`Digest` checksum if present.
+    ///
+    /// Use [`crate::download::download`] to resume after a dropped connection.
+    pub async fn download_to(self, path: impl AsRef<Path>) -> HttpResult<u64> {
+        self.download_to_with(path, &DownloadOptions::default())
             .await
-            .map_err(crate::error::HttpError::from)
+    }
+
+    pub async fn download_to_with(
+        self,
+        path: impl AsRef<Path>,
+        options: &DownloadOptions,
+    ) -> HttpResult<u64> {
+        crate::download::download_response(self, path.as_ref(), options).await
     }
 
     pub async fn text(self) -> crate::error::HttpResult<String> {
-        self.inner
-            .text()
+        let bytes = self.json_bytes().await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn url_display(&self) -> String {
+        self.url
+            .as_ref()
+            .map_or_else(|| "<unknown URL>".to_string(), ToString::to_string)
+    }
+}
+
+fn is_json_content_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime == "application/json" || mime.ends_with("+json")
+}
+
+pub(crate) fn decode_body<T: DeserializeOwned>(
+    status: HttpStatusCode,
+    content_type: Option<&str>,
+    bytes: &Bytes,
+    url: &str,
+) -> HttpResult<T> {
+    let no_content = matches!(
+        status,
+        HttpStatusCode::NoContent | HttpStatusCode::ResetContent | HttpStatusCode::NotModified
+    );
+    if no_content || bytes.is_empty() {
+        return serde_json::from_value(serde_json::Value::Null).map_err(|e| {
+            HttpError::Custom(format!(
+                "Empty response body from {} (status: {}) cannot be decoded: {}",
+                url, status, e
+            ))
+        });
+    }
+
+    if let Some(content_type) = content_type {
+        if !is_json_content_type(content_type) {
+            return Err(HttpError::UnexpectedContentType(content_type.to_string()));
+        }
+    }
+
+    serde_json::from_slice(bytes).map_err(|e| {
+        HttpError::Custom(format!(
+            "Failed to deserialize JSON response from {} (status: {}). \
+            Response body: {}. \
+            Serde error: {}",
+            url,
+            status,
+            String::from_utf8_lossy(bytes),
+            e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Order {
+        id: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct ApiError {
+        message: String,
+    }
+
+    fn json_response(status: HttpStatusCode, body: &str) -> HttpResponse {
+        HttpResponse::new(status)
+            .with_header("content-type", "application/json")
+            .with_body(body.to_string())
+    }
+
+    fn status_error(status: HttpStatusCode, content_type: &str, body: &str) -> HttpError {
+        let mut headers = HttpHeaders::new();
+        headers.insert("content-type", content_type).unwrap();
+        HttpError::Status {
+            status,
+            headers,
+            body: Bytes::from(body.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_success_body_as_t() {
+        let response = json_response(HttpStatusCode::Ok, r#"{"id":"o-1"}"#);
+        let decoded = response.decode::<Order, ApiError>().await.unwrap();
+        assert_eq!(decoded.status, HttpStatusCode::Ok);
+        assert_eq!(decoded.into_result().unwrap(), Order { id: "o-1".into() });
+    }
+
+    #[tokio::test]
+    async fn decodes_empty_no_content_as_unit() {
+        let response = HttpResponse::new(HttpStatusCode::NoContent);
+        let decoded = response.decode::<(), ApiError>().await.unwrap();
+        assert!(decoded.result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn from_result_decodes_client_status_errors_as_e() {
+        let error = status_error(
+            HttpStatusCode::BadRequest,
+            "application/json",
+            r#"{"message":"insufficient funds"}"#,
+        );
+        let decoded = Decoded::<Order, ApiError>::from_result(Err(error))
             .await
-            .map_err(crate::error::HttpError::from)
+            .unwrap();
+        assert_eq!(decoded.status, HttpStatusCode::BadRequest);
+        assert_eq!(
+            decoded.raw,
+            Bytes::from(r#"{"message":"insufficient funds"}"#)
+        );
+        assert_eq!(
+            decoded.into_result().unwrap_err(),
+            ApiError {
+                message: "insufficient funds".into()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn from_result_passes_other_errors_through() {
+        let error = HttpError::Config("boom".into());
+        let result = Decoded::<Order, ApiError>::from_result(Err(error)).await;
+        assert!(matches!(result, Err(HttpError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_non_json_content_type() {
+        let error = status_error(HttpStatusCode::BadGateway, "text/html", "<html>");
+        let result = Decoded::<Order, ApiError>::from_result(Err(error)).await;
+        assert!(matches!(result, Err(HttpError::UnexpectedContentType(_))));
+    }
+
+    #[test]
+    fn error_decode_body_reports_parse_failures() {
+        let error = status_error(HttpStatusCode::BadRequest, "application/json", "{oops");
+        assert!(error.decode_body::<ApiError>().is_err());
+
+        let error = status_error(
+            HttpStatusCode::BadRequest,
+            "application/json",
+            r#"{"message":"bad"}"#,
+        );
+        assert_eq!(error.decode_body::<ApiError>().unwrap().message, "bad");
+        assert!(HttpError::Config("x".into())
+            .decode_body::<ApiError>()
+            .is_err());
     }
 }
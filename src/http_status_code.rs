@@ -159,19 +159,19 @@ impl HttpStatusCode {
     /// Check if the status code indicates success (2xx)
     pub fn is_success(&self) -> bool {
         let code = self.as_u16();
-        code >= 200 && code < 300
+        (200..300).contains(&code)
     }
 
     /// Check if the status code indicates a client error (4xx)
     pub fn is_client_error(&self) -> bool {
         let code = self.as_u16();
-        code >= 400 && code < 500
+        (400..500).contains(&code)
     }
 
     /// Check if the status code indicates a server error (5xx)
     pub fn is_server_error(&self) -> bool {
         let code = self.as_u16();
-        code >= 500 && code < 600
+        (500..600).contains(&code)
     }
 
     /// Check if the status code indicates an error (4xx or 5xx)
@@ -182,19 +182,24 @@ impl HttpStatusCode {
     /// Check if the status code indicates a redirection (3xx)
     pub fn is_redirection(&self) -> bool {
         let code = self.as_u16();
-        code >= 300 && code < 400
+        (300..400).contains(&code)
     }
 
     /// Check if the status code indicates an informational response (1xx)
     pub fn is_informational(&self) -> bool {
         let code = self.as_u16();
-        code >= 100 && code < 200
+        (100..200).contains(&code)
     }
 }
 
-impl From<reqwest::StatusCode> for HttpStatusCode {
-    fn from(status: reqwest::StatusCode) -> Self {
-        let code = status.as_u16();
+impl From<http::StatusCode> for HttpStatusCode {
+    fn from(status: http::StatusCode) -> Self {
+        status.as_u16().into()
+    }
+}
+
+impl From<u16> for HttpStatusCode {
+    fn from(code: u16) -> Self {
         match code {
             100 => HttpStatusCode::Continue,
             101 => HttpStatusCode::SwitchingProtocols,
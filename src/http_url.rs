@@ -1,3 +1,5 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 /**
  * Copyright 2025-present Coinbase Global, Inc.
  *
@@ -13,10 +15,9 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use reqwest::Url;
-use std::fmt;
+use url::Url;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct HttpUrl(pub Url);
 
 #[derive(Debug)]
@@ -36,10 +37,189 @@ impl HttpUrl {
             .map(HttpUrl)
             .map_err(|e| HttpUrlError(e.to_string()))
     }
+    /// Resolve `path` against this URL using RFC 3986 reference resolution.
+    ///
+    /// Note that this replaces the last segment of the base path (`/api/v1` + `portfolios`
+    /// gives `/api/portfolios`) and that an absolute path drops the base path entirely. Use
+    /// [`HttpUrl::append_path`] or [`HttpUrl::resolve`] to keep a base path prefix.
     pub fn join(&self, path: &str) -> Result<Self, HttpUrlError> {
         self.0
             .join(path)
             .map(HttpUrl)
             .map_err(|e| HttpUrlError(e.to_string()))
     }
+
+    /// Append `path` (and any `?query`) to this URL's path, keeping the base path prefix.
+    ///
+    /// Leading, trailing and repeated slashes are normalized, so `https://host/api/v1/`
+    /// with `/portfolios` and `https://host/api/v1` with `portfolios` both give
+    /// `https://host/api/v1/portfolios`. `.` and `..` segments are resolved within the
+    /// appended path; a `..` that would climb into the base path is an error.
+    pub fn append_path(&self, path: &str) -> Result<Self, HttpUrlError> {
+        let (path, fragment) = match path.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (path, None),
+        };
+        let (path, query) = match path.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path, None),
+        };
+
+        let mut segments: Vec<&str> = self.0.path().split('/').filter(|s| !s.is_empty()).collect();
+        let prefix_len = segments.len();
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            match segment.to_ascii_lowercase().as_str() {
+                "." | "%2e" => {}
+                ".." | ".%2e" | "%2e." | "%2e%2e" => {
+                    if segments.len() == prefix_len {
+                        return Err(HttpUrlError(format!(
+                            "'{path}' escapes the base path of {self}"
+                        )));
+                    }
+                    segments.pop();
+                }
+                _ => segments.push(segment),
+            }
+        }
+        let mut joined = format!("/{}", segments.join("/"));
+        if path.ends_with('/') && joined.len() > 1 {
+            joined.push('/');
+        }
+
+        let mut url = self.0.clone();
+        if url.cannot_be_a_base() {
+            return Err(HttpUrlError(format!(
+                "{} cannot be used as a base URL",
+                url
+            )));
+        }
+        url.set_path(&joined);
+        if let Some(query) = query.filter(|q| !q.is_empty()) {
+            let merged = match url.query() {
+                Some(existing) if !existing.is_empty() => format!("{existing}&{query}"),
+                _ => query.to_string(),
+            };
+            url.set_query(Some(&merged));
+        }
+        if fragment.is_some() {
+            url.set_fragment(fragment);
+        }
+        Ok(HttpUrl(url))
+    }
+
+    /// Resolve a request target against this base URL.
+    ///
+    /// Relative targets are appended with [`HttpUrl::append_path`]. Absolute URLs (including
+    /// protocol-relative `//host/...`) are used as-is, but only if they point at the same
+    /// scheme, host and port as the base unless `allow_foreign_host` is set.
+    pub fn resolve(&self, target: &str, allow_foreign_host: bool) -> Result<Self, HttpUrlError> {
+        let absolute = if Self::is_absolute(target) {
+            Some(HttpUrl::parse(target)?)
+        } else if target.starts_with("//") {
+            Some(HttpUrl::parse(&format!("{}:{}", self.0.scheme(), target))?)
+        } else {
+            None
+        };
+        match absolute {
+            Some(url) if allow_foreign_host || self.same_origin(&url) => Ok(url),
+            Some(url) => Err(HttpUrlError(format!(
+                "{} points at a different host than the base URL {}",
+                url, self
+            ))),
+            None => self.append_path(target),
+        }
+    }
+
+    /// Whether `target` starts with a `scheme://` prefix, as opposed to a path that merely
+    /// contains `://` somewhere (e.g. in a query parameter)
+    pub fn is_absolute(target: &str) -> bool {
+        let Some((scheme, _)) = target.split_once("://") else {
+            return false;
+        };
+        let mut chars = scheme.chars();
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    }
+
+    /// Whether both URLs share a scheme, host and port
+    pub fn same_origin(&self, other: &HttpUrl) -> bool {
+        self.0.origin() == other.0.origin()
+    }
+}
+
+impl fmt::Display for HttpUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for HttpUrl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpUrl {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let url = String::deserialize(deserializer)?;
+        HttpUrl::parse(&url).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> HttpUrl {
+        HttpUrl::parse("https://host/api/v1").unwrap()
+    }
+
+    #[test]
+    fn append_path_keeps_the_base_prefix() {
+        for path in ["/portfolios", "portfolios", "//portfolios/"] {
+            let url = base().append_path(path).unwrap();
+            assert_eq!(url.0.path().trim_end_matches('/'), "/api/v1/portfolios");
+        }
+        let url = base().append_path("orders?limit=5#top").unwrap();
+        assert_eq!(url.to_string(), "https://host/api/v1/orders?limit=5#top");
+    }
+
+    #[test]
+    fn dot_segments_cannot_escape_the_base_prefix() {
+        let url = base().append_path("a/./b/../c").unwrap();
+        assert_eq!(url.0.path(), "/api/v1/a/c");
+        for path in [
+            "..",
+            "../admin",
+            "a/../../admin",
+            "%2e%2e/admin",
+            ".%2E/admin",
+        ] {
+            assert!(base().append_path(path).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn only_a_leading_scheme_makes_a_target_absolute() {
+        assert!(HttpUrl::is_absolute("https://host/x"));
+        assert!(HttpUrl::is_absolute("wss+v2://host"));
+        assert!(!HttpUrl::is_absolute("/redirect?to=https://evil/x"));
+        assert!(!HttpUrl::is_absolute("orders?next=https://evil"));
+        assert!(!HttpUrl::is_absolute("1http://host"));
+        assert!(!HttpUrl::is_absolute("://host"));
+    }
+
+    #[test]
+    fn resolve_keeps_embedded_urls_relative_and_checks_origins() {
+        let url = base()
+            .resolve("/redirect?to=https://evil/x", false)
+            .unwrap();
+        assert_eq!(url.0.host_str(), Some("host"));
+        assert_eq!(url.0.path(), "/api/v1/redirect");
+
+        assert!(base().resolve("https://host/other", false).is_ok());
+        assert!(base().resolve("https://evil/other", false).is_err());
+        assert!(base().resolve("//evil/other", false).is_err());
+        assert!(base().resolve("https://evil/other", true).is_ok());
+    }
 }
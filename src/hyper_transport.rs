@@ -0,0 +1,264 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_headers::HttpHeaders;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::http_status_code::HttpStatusCode;
+use crate::http_url::HttpUrl;
+use crate::tls::TlsConfig;
+use crate::transport::{header_map, wire_body, Transport, WireBody};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyDataStream, BodyExt, Empty, Full, StreamBody};
+use hyper::body::Frame;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use std::io;
+use std::time::Duration;
+
+/// The request body type used by [`HyperTransport`]
+pub type HyperBody = UnsyncBoxBody<Bytes, io::Error>;
+
+/// The hyper client type wrapped by [`HyperTransport`]
+pub type HyperClient = Client<HttpsConnector<HttpConnector>, HyperBody>;
+
+/// A transport backed by hyper, for callers that need control over HTTP/2 connections.
+///
+/// Redirects are not followed.
+#[derive(Debug, Clone)]
+pub struct HyperTransport {
+    client: HyperClient,
+}
+
+impl HyperTransport {
+    pub fn new() -> HttpResult<Self> {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> HyperTransportBuilder {
+        HyperTransportBuilder::new()
+    }
+
+    /// Use a hyper client configured elsewhere
+    pub fn from_client(client: HyperClient) -> Self {
+        Self { client }
+    }
+
+    pub fn client(&self) -> &HyperClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl Transport for HyperTransport {
+    async fn send(&self, request: &HttpRequest, url: HttpUrl) -> HttpResult<HttpResponse> {
+        let mut builder = http::Request::builder()
+            .method(http::Method::from(request.method.clone()))
+            .uri(url.0.as_str());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = header_map(request)?;
+        }
+        let body: HyperBody = match wire_body(request)? {
+            WireBody::Empty => Empty::new().map_err(|never| match never {}).boxed_unsync(),
+            WireBody::Bytes(bytes) => Full::new(bytes)
+                .map_err(|never| match never {})
+                .boxed_unsync(),
+            WireBody::Stream(stream) => StreamBody::new(stream.map_ok(Frame::data)).boxed_unsync(),
+        };
+        let hyper_request = builder.body(body).map_err(transport_error)?;
+
+        let response = self
+            .client
+            .request(hyper_request)
+            .await
+            .map_err(transport_error)?;
+        let (parts, body) = response.into_parts();
+        let status = HttpStatusCode::from(parts.status);
+        let headers = HttpHeaders::from(&parts.headers);
+        let http_response = if request.is_streaming_response() && !status.is_error() {
+            HttpResponse::streaming(
+                status,
+                headers,
+                BodyDataStream::new(body).map_err(transport_error),
+            )
+        } else {
+            let body = body.collect().await.map_err(transport_error)?.to_bytes();
+            HttpResponse::from_parts(status, headers, body)
+        };
+        Ok(http_response.with_url(url))
+    }
+}
+
+/// HTTP/2, pool and TLS settings for a [`HyperTransport`]
+#[derive(Clone, Default)]
+pub struct HyperTransportBuilder {
+    connect_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    http2_only: bool,
+    http2_keep_alive_interval: Option<Duration>,
+    http2_keep_alive_timeout: Option<Duration>,
+    http2_keep_alive_while_idle: bool,
+    http2_initial_stream_window_size: Option<u32>,
+    http2_initial_connection_window_size: Option<u32>,
+    http2_adaptive_window: bool,
+    http2_max_frame_size: Option<u32>,
+    http2_max_concurrent_reset_streams: Option<usize>,
+    tls: Option<TlsConfig>,
+}
+
+impl HyperTransportBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Speak only HTTP/2: negotiated with ALPN over TLS, prior knowledge over plain TCP
+    pub fn http2_only(mut self) -> Self {
+        self.http2_only = true;
+        self
+    }
+
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    pub fn http2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.http2_keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Keep sending pings when no requests are in flight
+    pub fn http2_keep_alive_while_idle(mut self, enabled: bool) -> Self {
+        self.http2_keep_alive_while_idle = enabled;
+        self
+    }
+
+    pub fn http2_initial_stream_window_size(mut self, size: u32) -> Self {
+        self.http2_initial_stream_window_size = Some(size);
+        self
+    }
+
+    pub fn http2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.http2_initial_connection_window_size = Some(size);
+        self
+    }
+
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2_adaptive_window = enabled;
+        self
+    }
+
+    pub fn http2_max_frame_size(mut self, size: u32) -> Self {
+        self.http2_max_frame_size = Some(size);
+        self
+    }
+
+    pub fn http2_max_concurrent_reset_streams(mut self, max: usize) -> Self {
+        self.http2_max_concurrent_reset_streams = Some(max);
+        self
+    }
+
+    /// Use custom roots, a client identity and/or public key pins instead of the
+    /// built-in roots
+    pub fn tls(mut self, config: TlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    pub fn build(self) -> HttpResult<HyperTransport> {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_connect_timeout(self.connect_timeout);
+        http.set_keepalive(self.tcp_keepalive);
+
+        let mut tls = self.tls.clone().unwrap_or_default().build()?;
+        // hyper-rustls advertises the protocols enabled below
+        tls.alpn_protocols.clear();
+        let https = HttpsConnectorBuilder::new()
+            .with_tls_config(tls)
+            .https_or_http();
+        let connector = if self.http2_only {
+            https.enable_http2().wrap_connector(http)
+        } else {
+            https.enable_http1().enable_http2().wrap_connector(http)
+        };
+
+        let mut builder = Client::builder(TokioExecutor::new());
+        builder
+            .timer(TokioTimer::new())
+            .pool_timer(TokioTimer::new());
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max);
+        }
+        builder
+            .http2_only(self.http2_only)
+            .http2_keep_alive_interval(self.http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(self.http2_keep_alive_while_idle)
+            .http2_initial_stream_window_size(self.http2_initial_stream_window_size)
+            .http2_initial_connection_window_size(self.http2_initial_connection_window_size)
+            .http2_adaptive_window(self.http2_adaptive_window)
+            .http2_max_frame_size(self.http2_max_frame_size);
+        if let Some(timeout) = self.http2_keep_alive_timeout {
+            builder.http2_keep_alive_timeout(timeout);
+        }
+        if let Some(max) = self.http2_max_concurrent_reset_streams {
+            builder.http2_max_concurrent_reset_streams(max);
+        }
+        Ok(HyperTransport {
+            client: builder.build(connector),
+        })
+    }
+}
+
+fn transport_error(error: impl std::error::Error + Send + Sync + 'static) -> HttpError {
+    if let Some(mismatch) = crate::tls::find_pin_mismatch(&error) {
+        return HttpError::PinMismatch {
+            host: mismatch.host,
+            observed: mismatch.observed,
+        };
+    }
+    HttpError::Transport(Box::new(error))
+}
@@ -0,0 +1,208 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_method::HttpMethod;
+use crate::http_request::{HttpRequest, RequestBody};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub const CLIENT_ORDER_ID_FIELD: &str = "client_order_id";
+
+/// Where an idempotency key is carried on the wire
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdempotencyStrategy {
+    /// Send the key in a request header
+    Header(String),
+    /// Put the key in a top-level field of the JSON body, e.g. `client_order_id`.
+    /// A value the caller already set in that field is used as the key.
+    JsonField(String),
+}
+
+impl IdempotencyStrategy {
+    /// The `Idempotency-Key` header
+    pub fn header() -> Self {
+        IdempotencyStrategy::Header(IDEMPOTENCY_KEY_HEADER.to_string())
+    }
+
+    /// The `client_order_id` body field used by order endpoints
+    pub fn client_order_id() -> Self {
+        IdempotencyStrategy::JsonField(CLIENT_ORDER_ID_FIELD.to_string())
+    }
+}
+
+/// A new random (v4) UUID key
+pub fn generate_key() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Attach the request's idempotency key once, before the first attempt, and return it.
+///
+/// The key is stored on the request so every retry of the same logical request sends it
+/// unchanged.
+pub(crate) fn apply(
+    request: &mut HttpRequest,
+    strategy: &IdempotencyStrategy,
+) -> HttpResult<String> {
+    match strategy {
+        IdempotencyStrategy::Header(name) => {
+            let key = request
+                .idempotency_key()
+                .map(str::to_string)
+                .unwrap_or_else(generate_key);
+            request
+                .add_header(name, &key)
+                .map_err(|e| HttpError::Config(format!("Invalid idempotency header: {e}")))?;
+            request.set_idempotency_key(key.clone());
+            Ok(key)
+        }
+        IdempotencyStrategy::JsonField(field) => {
+            let explicit = request.idempotency_key().map(str::to_string);
+            if request.body == RequestBody::Empty {
+                request.body = RequestBody::Json(Value::Object(Default::default()));
+            }
+            let RequestBody::Json(body) = &mut request.body else {
+                return Err(HttpError::Config(format!(
+                    "Cannot set idempotency field '{field}': the request body is not JSON"
+                )));
+            };
+            let object = body.as_object_mut().ok_or_else(|| {
+                HttpError::Config(format!(
+                    "Cannot set idempotency field '{field}': the JSON body is not an object"
+                ))
+            })?;
+            let key = match (explicit, object.get(field)) {
+                (Some(key), _) => {
+                    object.insert(field.clone(), Value::String(key.clone()));
+                    key
+                }
+                (None, Some(Value::String(existing))) => existing.clone(),
+                // Keep a caller's numeric or other id as it is and record its JSON text
+                (None, Some(other)) => other.to_string(),
+                (None, None) => {
+                    let key = generate_key();
+                    object.insert(field.clone(), Value::String(key.clone()));
+                    key
+                }
+            };
+            request.set_idempotency_key(key.clone());
+            Ok(key)
+        }
+    }
+}
+
+/// [`apply`] for a client-wide strategy, which also meets requests it doesn't fit: a
+/// [`IdempotencyStrategy::JsonField`] falls back to the `Idempotency-Key` header when the
+/// body is not a JSON object, rather than failing or rewriting the body.
+pub(crate) fn apply_default(
+    request: &mut HttpRequest,
+    strategy: &IdempotencyStrategy,
+) -> HttpResult<String> {
+    match strategy {
+        IdempotencyStrategy::JsonField(_)
+            if !matches!(request.body, RequestBody::Json(Value::Object(_))) =>
+        {
+            apply(request, &IdempotencyStrategy::header())
+        }
+        _ => apply(request, strategy),
+    }
+}
+
+/// Attach a key the way the client does before the first attempt: with the request's own
+/// strategy, or else with `default` for POST and PATCH. Returns the key, if one applies.
+pub(crate) fn assign(
+    request: &mut HttpRequest,
+    default: Option<&IdempotencyStrategy>,
+) -> HttpResult<Option<String>> {
+    match (request.idempotency().cloned(), default) {
+        (Some(strategy), _) => apply(request, &strategy).map(Some),
+        (None, Some(strategy)) if matches!(request.method, HttpMethod::Post | HttpMethod::Patch) => {
+            apply_default(request, strategy).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn post(body: Value) -> HttpRequest {
+        HttpRequest::new(HttpMethod::Post, "/orders")
+            .unwrap()
+            .with_json_body(body)
+    }
+
+    #[test]
+    fn json_field_generates_a_key_once_and_reuses_it() {
+        let mut request = post(json!({"side": "BUY"}));
+        let key = apply(&mut request, &IdempotencyStrategy::client_order_id()).unwrap();
+        let again = apply(&mut request, &IdempotencyStrategy::client_order_id()).unwrap();
+        assert_eq!(key, again);
+        assert_eq!(
+            request.body,
+            RequestBody::Json(json!({"side": "BUY", "client_order_id": key}))
+        );
+    }
+
+    #[test]
+    fn existing_non_string_ids_are_left_unchanged() {
+        let mut request = post(json!({"client_order_id": 42}));
+        let key = apply(&mut request, &IdempotencyStrategy::client_order_id()).unwrap();
+        assert_eq!(key, "42");
+        assert_eq!(
+            request.body,
+            RequestBody::Json(json!({"client_order_id": 42}))
+        );
+    }
+
+    #[test]
+    fn explicit_json_field_on_a_non_json_body_is_an_error() {
+        let mut request = HttpRequest::new(HttpMethod::Post, "/upload")
+            .unwrap()
+            .with_body("raw");
+        let result = apply(&mut request, &IdempotencyStrategy::client_order_id());
+        assert!(matches!(result, Err(HttpError::Config(_))));
+    }
+
+    #[test]
+    fn client_wide_json_field_falls_back_to_the_header() {
+        for mut request in [
+            HttpRequest::new(HttpMethod::Post, "/upload")
+                .unwrap()
+                .with_body("raw"),
+            HttpRequest::new(HttpMethod::Post, "/cancel").unwrap(),
+            post(json!([1, 2])),
+        ] {
+            let body = request.body.clone();
+            let key = apply_default(&mut request, &IdempotencyStrategy::client_order_id()).unwrap();
+            assert_eq!(
+                request.headers.get(IDEMPOTENCY_KEY_HEADER),
+                Some(key.as_str())
+            );
+            assert_eq!(request.body, body);
+        }
+
+        let mut request = post(json!({}));
+        let key = apply_default(&mut request, &IdempotencyStrategy::client_order_id()).unwrap();
+        assert_eq!(
+            request.body,
+            RequestBody::Json(json!({"client_order_id": key}))
+        );
+        assert!(request.headers.get(IDEMPOTENCY_KEY_HEADER).is_none());
+    }
+}
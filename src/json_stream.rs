@@ -0,0 +1,475 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+
+const DEFAULT_MAX_ELEMENT_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct JsonStreamOptions {
+    /// Largest single element that will be buffered before it is rejected
+    pub max_element_size: usize,
+}
+
+impl Default for JsonStreamOptions {
+    fn default() -> Self {
+        Self {
+            max_element_size: DEFAULT_MAX_ELEMENT_SIZE,
+        }
+    }
+}
+
+/// Decode newline-delimited JSON into a stream of `T`, one item per non-empty line.
+///
+/// A line that fails to decode yields an [`HttpError::JsonElement`] carrying its byte offset
+/// and the stream carries on with the next line.
+pub fn ndjson<T, S>(bytes: S, options: JsonStreamOptions) -> impl Stream<Item = HttpResult<T>>
+where
+    T: DeserializeOwned,
+    S: Stream<Item = HttpResult<Bytes>> + Send + 'static,
+{
+    decode_stream(bytes, NdjsonSplitter::new(options.max_element_size))
+}
+
+/// Decode a top-level JSON array into a stream of `T`, one item per element.
+///
+/// Only the element currently being read is held in memory. Elements that are well-formed
+/// JSON but don't match `T` yield a per-item error; a syntax error in the array itself
+/// ends the stream.
+pub fn json_array<T, S>(bytes: S, options: JsonStreamOptions) -> impl Stream<Item = HttpResult<T>>
+where
+    T: DeserializeOwned,
+    S: Stream<Item = HttpResult<Bytes>> + Send + 'static,
+{
+    decode_stream(bytes, ArraySplitter::new(options.max_element_size))
+}
+
+enum Piece {
+    Element { offset: u64, bytes: Vec<u8> },
+    Error { offset: u64, message: String },
+    Fatal { offset: u64, message: String },
+}
+
+trait Splitter {
+    fn feed(&mut self, chunk: &[u8], out: &mut VecDeque<Piece>);
+    fn finish(&mut self, out: &mut VecDeque<Piece>);
+}
+
+struct DecodeState<P> {
+    bytes: BoxStream<'static, HttpResult<Bytes>>,
+    splitter: P,
+    pending: VecDeque<Piece>,
+    done: bool,
+}
+
+fn decode_stream<T, S, P>(bytes: S, splitter: P) -> impl Stream<Item = HttpResult<T>>
+where
+    T: DeserializeOwned,
+    S: Stream<Item = HttpResult<Bytes>> + Send + 'static,
+    P: Splitter,
+{
+    let state = DecodeState {
+        bytes: bytes.boxed(),
+        splitter,
+        pending: VecDeque::new(),
+        done: false,
+    };
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(piece) = state.pending.pop_front() {
+                let item = match piece {
+                    Piece::Element { offset, bytes } => serde_json::from_slice::<T>(&bytes)
+                        .map_err(|source| HttpError::JsonElement { offset, source }),
+                    Piece::Error { offset, message } => {
+                        Err(HttpError::JsonStream { offset, message })
+                    }
+                    Piece::Fatal { offset, message } => {
+                        state.done = true;
+                        state.pending.clear();
+                        Err(HttpError::JsonStream { offset, message })
+                    }
+                };
+                return Some((item, state));
+            }
+            if state.done {
+                return None;
+            }
+            match state.bytes.next().await {
+                Some(Ok(chunk)) => state.splitter.feed(&chunk, &mut state.pending),
+                Some(Err(e)) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+                None => {
+                    state.done = true;
+                    state.splitter.finish(&mut state.pending);
+                }
+            }
+        }
+    })
+}
+
+struct NdjsonSplitter {
+    max_element_size: usize,
+    line: Vec<u8>,
+    line_offset: u64,
+    offset: u64,
+    // Set when the current line grew past the size limit and is being discarded
+    skipping: bool,
+}
+
+impl NdjsonSplitter {
+    fn new(max_element_size: usize) -> Self {
+        Self {
+            max_element_size,
+            line: Vec::new(),
+            line_offset: 0,
+            offset: 0,
+            skipping: false,
+        }
+    }
+
+    fn emit_line(&mut self, out: &mut VecDeque<Piece>) {
+        if !self.skipping {
+            let trimmed = self.line.trim_ascii();
+            if !trimmed.is_empty() {
+                out.push_back(Piece::Element {
+                    offset: self.line_offset,
+                    bytes: trimmed.to_vec(),
+                });
+            }
+        }
+        self.line.clear();
+        self.skipping = false;
+    }
+}
+
+impl Splitter for NdjsonSplitter {
+    fn feed(&mut self, chunk: &[u8], out: &mut VecDeque<Piece>) {
+        for &byte in chunk {
+            if self.line.is_empty() && !self.skipping {
+                self.line_offset = self.offset;
+            }
+            self.offset += 1;
+            if byte == b'\n' {
+                self.emit_line(out);
+                continue;
+            }
+            if self.skipping {
+                continue;
+            }
+            if self.line.len() >= self.max_element_size {
+                out.push_back(Piece::Error {
+                    offset: self.line_offset,
+                    message: format!("line exceeds {} bytes", self.max_element_size),
+                });
+                self.line.clear();
+                self.skipping = true;
+                continue;
+            }
+            self.line.push(byte);
+        }
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Piece>) {
+        self.emit_line(out);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ArrayState {
+    Start,
+    BeforeElement,
+    // After a ',', where another element is required
+    AfterComma,
+    InElement,
+    AfterArray,
+    Failed,
+}
+
+struct ArraySplitter {
+    max_element_size: usize,
+    state: ArrayState,
+    element: Vec<u8>,
+    element_offset: u64,
+    offset: u64,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ArraySplitter {
+    fn new(max_element_size: usize) -> Self {
+        Self {
+            max_element_size,
+            state: ArrayState::Start,
+            element: Vec::new(),
+            element_offset: 0,
+            offset: 0,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    fn fail(&mut self, out: &mut VecDeque<Piece>, offset: u64, message: String) {
+        self.state = ArrayState::Failed;
+        out.push_back(Piece::Fatal { offset, message });
+    }
+
+    fn start_element(&mut self, offset: u64, byte: u8) {
+        self.state = ArrayState::InElement;
+        self.element_offset = offset;
+        self.depth = 0;
+        self.in_string = false;
+        self.escaped = false;
+        self.element.push(byte);
+        match byte {
+            b'{' | b'[' => self.depth = 1,
+            b'"' => self.in_string = true,
+            _ => {}
+        }
+    }
+
+    fn emit_element(&mut self, out: &mut VecDeque<Piece>) {
+        let bytes = std::mem::take(&mut self.element);
+        out.push_back(Piece::Element {
+            offset: self.element_offset,
+            bytes: bytes.trim_ascii_end().to_vec(),
+        });
+    }
+}
+
+impl Splitter for ArraySplitter {
+    fn feed(&mut self, chunk: &[u8], out: &mut VecDeque<Piece>) {
+        for &byte in chunk {
+            let offset = self.offset;
+            self.offset += 1;
+            match self.state {
+                ArrayState::Failed => return,
+                ArrayState::Start => match byte {
+                    b'[' => self.state = ArrayState::BeforeElement,
+                    b if b.is_ascii_whitespace() => {}
+                    b => self.fail(
+                        out,
+                        offset,
+                        format!("expected '[' but found '{}'", b as char),
+                    ),
+                },
+                ArrayState::BeforeElement => match byte {
+                    b if b.is_ascii_whitespace() => {}
+                    b']' => self.state = ArrayState::AfterArray,
+                    b',' => self.fail(out, offset, "unexpected ','".to_string()),
+                    b => self.start_element(offset, b),
+                },
+                ArrayState::AfterComma => match byte {
+                    b if b.is_ascii_whitespace() => {}
+                    b']' => self.fail(out, offset, "trailing ',' before ']'".to_string()),
+                    b',' => self.fail(out, offset, "unexpected ','".to_string()),
+                    b => self.start_element(offset, b),
+                },
+                ArrayState::InElement => {
+                    if self.in_string {
+                        if self.escaped {
+                            self.escaped = false;
+                        } else if byte == b'\\' {
+                            self.escaped = true;
+                        } else if byte == b'"' {
+                            self.in_string = false;
+                        }
+                    } else {
+                        match byte {
+                            b'"' => self.in_string = true,
+                            b'{' | b'[' => self.depth += 1,
+                            b'}' | b']' if self.depth > 0 => self.depth -= 1,
+                            b',' if self.depth == 0 => {
+                                self.emit_element(out);
+                                self.state = ArrayState::AfterComma;
+                                continue;
+                            }
+                            b']' => {
+                                self.emit_element(out);
+                                self.state = ArrayState::AfterArray;
+                                continue;
+                            }
+                            b'}' => {
+                                self.fail(out, offset, "unbalanced '}'".to_string());
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+                    if self.element.len() >= self.max_element_size {
+                        let element_offset = self.element_offset;
+                        self.fail(
+                            out,
+                            element_offset,
+                            format!("element exceeds {} bytes", self.max_element_size),
+                        );
+                        self.element.clear();
+                        continue;
+                    }
+                    self.element.push(byte);
+                }
+                ArrayState::AfterArray => {
+                    if !byte.is_ascii_whitespace() {
+                        self.fail(
+                            out,
+                            offset,
+                            "unexpected data after the end of the array".to_string(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Piece>) {
+        match self.state {
+            ArrayState::AfterArray | ArrayState::Failed => {}
+            _ => {
+                let offset = self.offset;
+                self.fail(out, offset, "unexpected end of array".to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+    use serde_json::Value;
+
+    // Feed `body` in chunks of `chunk` bytes so element boundaries fall mid-chunk
+    fn chunks(body: &str, chunk: usize) -> impl Stream<Item = HttpResult<Bytes>> + Send + 'static {
+        let pieces: Vec<HttpResult<Bytes>> = body
+            .as_bytes()
+            .chunks(chunk)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        stream::iter(pieces)
+    }
+
+    async fn array(body: &str, chunk: usize) -> Vec<HttpResult<Value>> {
+        json_array::<Value, _>(chunks(body, chunk), JsonStreamOptions::default())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn splits_array_elements_across_chunks() {
+        let body = r#" [ {"a": [1, 2], "s": "x,]}"}, "q\"uote", 3 , null ] "#;
+        for chunk in [1, 3, 64] {
+            let items: Vec<Value> = json_array(chunks(body, chunk), JsonStreamOptions::default())
+                .try_collect()
+                .await
+                .unwrap();
+            assert_eq!(
+                items,
+                vec![
+                    serde_json::json!({"a": [1, 2], "s": "x,]}"}),
+                    Value::from("q\"uote"),
+                    Value::from(3),
+                    Value::Null
+                ]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_array_yields_nothing() {
+        assert!(array("[ ]", 1).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_trailing_comma_with_offset() {
+        let items = array("[1,]", 2).await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), &Value::from(1));
+        match &items[1] {
+            Err(HttpError::JsonStream { offset, .. }) => assert_eq!(*offset, 3),
+            other => panic!("expected a stream error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_leading_comma_and_trailing_data() {
+        assert!(matches!(
+            array("[,1]", 4).await.as_slice(),
+            [Err(HttpError::JsonStream { offset: 1, .. })]
+        ));
+        let items = array("[1] x", 8).await;
+        assert!(matches!(
+            items.last(),
+            Some(Err(HttpError::JsonStream { offset: 4, .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn truncated_array_is_an_error() {
+        let items = array(r#"[{"a":1}, {"b""#, 5).await;
+        assert!(items[0].is_ok());
+        assert!(matches!(items[1], Err(HttpError::JsonStream { .. })));
+    }
+
+    #[tokio::test]
+    async fn element_type_mismatch_is_per_item() {
+        let items: Vec<HttpResult<u32>> =
+            json_array(chunks(r#"[1, "two", 3]"#, 4), JsonStreamOptions::default())
+                .collect()
+                .await;
+        assert!(items[0].is_ok());
+        assert!(matches!(
+            items[1],
+            Err(HttpError::JsonElement { offset: 4, .. })
+        ));
+        assert_eq!(*items[2].as_ref().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn ndjson_skips_blank_lines_and_reports_bad_lines() {
+        let body = "{\"n\":1}\n\n  not json\n{\"n\":2}";
+        let items: Vec<HttpResult<Value>> = ndjson(chunks(body, 3), JsonStreamOptions::default())
+            .collect()
+            .await;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap()["n"], 1);
+        assert!(matches!(
+            items[1],
+            Err(HttpError::JsonElement { offset: 9, .. })
+        ));
+        assert_eq!(items[2].as_ref().unwrap()["n"], 2);
+    }
+
+    #[tokio::test]
+    async fn ndjson_rejects_oversized_lines_and_continues() {
+        let options = JsonStreamOptions {
+            max_element_size: 8,
+        };
+        let body = "\"0123456789\"\n1\n";
+        let items: Vec<HttpResult<Value>> = ndjson(chunks(body, 4), options).collect().await;
+        assert!(matches!(
+            items[0],
+            Err(HttpError::JsonStream { offset: 0, .. })
+        ));
+        assert_eq!(*items[1].as_ref().unwrap(), Value::from(1));
+    }
+}
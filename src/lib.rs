@@ -13,8 +13,22 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod amount;
+pub mod audit;
+pub mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
+pub mod coalesce;
+pub mod correlation;
 pub mod credentials;
+pub mod download;
+pub mod endpoint;
+pub mod environment;
 pub mod error;
+pub mod extensions;
+pub mod har;
+pub mod hedge;
 pub mod http_client;
 pub mod http_headers;
 pub mod http_method;
@@ -22,7 +36,21 @@ pub mod http_request;
 pub mod http_response;
 pub mod http_status_code;
 pub mod http_url;
+#[cfg(feature = "hyper")]
+pub mod hyper_transport;
+pub mod idempotency;
 pub mod interceptor;
+pub mod json_stream;
+pub mod multipart;
+pub mod rate_limit;
+#[cfg(feature = "reqwest")]
+pub mod reqwest_transport;
+#[cfg(feature = "rustls")]
+pub mod tls;
+pub mod transport;
+#[cfg(feature = "ureq")]
+pub mod ureq_transport;
 
 // Re-export commonly used types
+pub use amount::Amount;
 pub use http_status_code::HttpStatusCode;
@@ -0,0 +1,413 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_headers::HttpHeaders;
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{StreamExt, TryStreamExt};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
+
+type OneShotStream = Arc<Mutex<Option<BoxStream<'static, io::Result<Bytes>>>>>;
+
+#[derive(Clone)]
+pub enum PartBody {
+    Bytes(Bytes),
+    /// Read from disk each time the request is sent, so retries replay the file
+    File(PathBuf),
+    /// Can only be sent once; requests containing it are not retried
+    Reader(OneShotStream),
+}
+
+/// Reader bodies are equal only when they share the same stream
+impl PartialEq for PartBody {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PartBody::Bytes(a), PartBody::Bytes(b)) => a == b,
+            (PartBody::File(a), PartBody::File(b)) => a == b,
+            (PartBody::Reader(a), PartBody::Reader(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for PartBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartBody::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            PartBody::File(path) => write!(f, "File({})", path.display()),
+            PartBody::Reader(_) => write!(f, "Reader"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    headers: HttpHeaders,
+    body: PartBody,
+}
+
+impl Part {
+    pub fn text(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::new(name, PartBody::Bytes(Bytes::from(value.into())))
+    }
+
+    pub fn bytes(name: impl Into<String>, bytes: impl Into<Bytes>) -> Self {
+        Self::new(name, PartBody::Bytes(bytes.into())).with_content_type("application/octet-stream")
+    }
+
+    /// A file part streamed from disk, named after the file
+    pub fn file(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let filename = path.file_name().map(|f| f.to_string_lossy().into_owned());
+        let mut part =
+            Self::new(name, PartBody::File(path)).with_content_type("application/octet-stream");
+        part.filename = filename;
+        part
+    }
+
+    /// A part streamed from an async reader. The reader is consumed on first send.
+    pub fn reader(name: impl Into<String>, reader: impl AsyncRead + Send + 'static) -> Self {
+        let stream = ReaderStream::new(reader).boxed();
+        Self::new(name, PartBody::Reader(Arc::new(Mutex::new(Some(stream)))))
+            .with_content_type("application/octet-stream")
+    }
+
+    fn new(name: impl Into<String>, body: PartBody) -> Self {
+        Self {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            headers: HttpHeaders::new(),
+            body,
+        }
+    }
+
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Set a header from literal values.
+    ///
+    /// # Panics
+    ///
+    /// If the name or value is not a valid header; use [`Part::try_with_header`] for values that aren't known
+    /// to be valid.
+    pub fn with_header(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.try_with_header(name, value)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_with_header(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> HttpResult<Self> {
+        self.headers.insert(name, value)?;
+        Ok(self)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn body(&self) -> &PartBody {
+        &self.body
+    }
+
+    pub fn is_replayable(&self) -> bool {
+        !matches!(self.body, PartBody::Reader(_))
+    }
+
+    // Part headers are checked like request headers, so a content type can't smuggle in
+    // CR/LF; names and filenames are quoted with CR/LF replaced
+    fn head(&self, boundary: &str) -> HttpResult<Bytes> {
+        let mut head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            boundary,
+            escape_quoted(&self.name)
+        );
+        if let Some(filename) = &self.filename {
+            head.push_str(&format!("; filename=\"{}\"", escape_quoted(filename)));
+        }
+        head.push_str("\r\n");
+        if let Some(content_type) = &self.content_type {
+            http::HeaderValue::from_str(content_type).map_err(|e| HttpError::InvalidHeader {
+                name: "content-type".to_string(),
+                reason: e.to_string(),
+            })?;
+            head.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        Ok(Bytes::from(head))
+    }
+
+    // The `curl -F` option and value that send this part
+    fn curl_form(&self) -> (&'static str, String) {
+        let text = match &self.body {
+            PartBody::Bytes(bytes) if self.filename.is_none() => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        };
+        let (option, mut value) = match (text, &self.body) {
+            (Some(text), _) => ("--form-string", format!("{}={}", self.name, text)),
+            (None, PartBody::File(path)) => ("-F", format!("{}=@{}", self.name, path.display())),
+            // In-memory and reader bodies have to be saved to a file named after the part
+            (None, _) => (
+                "-F",
+                format!(
+                    "{}=@{}",
+                    self.name,
+                    self.filename.as_deref().unwrap_or(&self.name)
+                ),
+            ),
+        };
+        if text.is_none() {
+            if let Some(filename) = &self.filename {
+                value.push_str(&format!(";filename=\"{}\"", escape_quoted(filename)));
+            }
+            if let Some(content_type) = &self.content_type {
+                value.push_str(&format!(";type={}", content_type));
+            }
+            for (name, header) in self.headers.iter() {
+                value.push_str(&format!(";headers=\"{}: {}\"", name, escape_quoted(header)));
+            }
+        }
+        (option, value)
+    }
+
+    fn body_stream(&self) -> HttpResult<BoxStream<'static, io::Result<Bytes>>> {
+        match &self.body {
+            PartBody::Bytes(bytes) => {
+                Ok(stream::once(futures_util::future::ready(Ok(bytes.clone()))).boxed())
+            }
+            PartBody::File(path) => {
+                let path = path.clone();
+                Ok(
+                    stream::once(async move { tokio::fs::File::open(path).await })
+                        .map_ok(ReaderStream::new)
+                        .try_flatten()
+                        .boxed(),
+                )
+            }
+            PartBody::Reader(reader) => reader
+                .lock()
+                .map_err(|_| HttpError::Custom("Multipart reader lock poisoned".to_string()))?
+                .take()
+                .ok_or_else(|| {
+                    HttpError::Custom(format!(
+                        "Multipart part '{}' was streamed from a reader and has already been sent",
+                        self.name
+                    ))
+                }),
+        }
+    }
+}
+
+/// A `multipart/form-data` body whose parts are streamed when the request is sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Multipart {
+    pub fn new() -> Self {
+        Self {
+            boundary: generate_boundary(),
+            parts: Vec::new(),
+        }
+    }
+
+    pub fn with_part(mut self, part: Part) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    pub fn with_text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with_part(Part::text(name, value))
+    }
+
+    pub fn with_file(self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.with_part(Part::file(name, path))
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Whether the body can be rebuilt for another attempt
+    pub fn is_replayable(&self) -> bool {
+        self.parts.iter().all(Part::is_replayable)
+    }
+
+    /// The `curl` form options that send the same parts, each an option and its value
+    pub(crate) fn curl_forms(&self) -> Vec<(&'static str, String)> {
+        self.parts.iter().map(Part::curl_form).collect()
+    }
+
+    /// Build a fresh streaming body for one attempt
+    pub(crate) fn to_stream(&self) -> HttpResult<BoxStream<'static, io::Result<Bytes>>> {
+        let mut streams = Vec::with_capacity(self.parts.len() * 3 + 1);
+        for part in &self.parts {
+            let head = part.head(&self.boundary)?;
+            streams.push(stream::once(futures_util::future::ready(Ok(head))).boxed());
+            streams.push(part.body_stream()?);
+            streams.push(
+                stream::once(futures_util::future::ready(Ok(Bytes::from_static(b"\r\n")))).boxed(),
+            );
+        }
+        let tail = Bytes::from(format!("--{}--\r\n", self.boundary));
+        streams.push(stream::once(futures_util::future::ready(Ok(tail))).boxed());
+        Ok(stream::iter(streams).flatten().boxed())
+    }
+}
+
+fn escape_quoted(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(['\r', '\n'], " ")
+}
+
+fn generate_boundary() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("core-rs-boundary-{:016x}{:08x}", nanos, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+
+    async fn render(multipart: &Multipart) -> HttpResult<String> {
+        let chunks: Vec<Bytes> = multipart.to_stream()?.try_collect().await?;
+        Ok(String::from_utf8(chunks.concat()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn renders_parts_between_boundaries() {
+        let multipart = Multipart::new().with_text("side", "buy").with_part(
+            Part::bytes("doc", b"%PDF".to_vec())
+                .with_filename("id.pdf")
+                .with_content_type("application/pdf")
+                .with_header("X-Checksum", "abc"),
+        );
+        let b = multipart.boundary().to_string();
+        assert_eq!(
+            render(&multipart).await.unwrap(),
+            format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"side\"\r\n\r\nbuy\r\n\
+                 --{b}\r\nContent-Disposition: form-data; name=\"doc\"; filename=\"id.pdf\"\r\n\
+                 Content-Type: application/pdf\r\nx-checksum: abc\r\n\r\n%PDF\r\n--{b}--\r\n"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_crlf_in_content_type() {
+        let multipart = Multipart::new()
+            .with_part(Part::text("a", "b").with_content_type("text/plain\r\nX-Injected: 1"));
+        assert!(matches!(
+            render(&multipart).await,
+            Err(HttpError::InvalidHeader { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn escapes_names_and_filenames() {
+        let multipart = Multipart::new().with_part(
+            Part::bytes("n\"ame", b"x".to_vec()).with_filename("evil\r\nX-Injected: 1.txt"),
+        );
+        let body = render(&multipart).await.unwrap();
+        assert!(body.contains("name=\"n\\\"ame\"; filename=\"evil  X-Injected: 1.txt\"\r\n"));
+        assert!(!body.contains("\r\nX-Injected"));
+    }
+
+    #[test]
+    fn part_headers_are_validated() {
+        assert!(Part::text("a", "b")
+            .try_with_header("X-Ok", "bad\nvalue")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn reader_parts_are_one_shot() {
+        let multipart = Multipart::new().with_part(Part::reader("r", &b"data"[..]));
+        assert!(!multipart.is_replayable());
+        assert!(render(&multipart)
+            .await
+            .unwrap()
+            .contains("\r\n\r\ndata\r\n"));
+        assert!(render(&multipart).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn file_parts_are_read_on_every_send() {
+        let path = std::env::temp_dir().join(format!("core-rs-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "from disk").unwrap();
+        let multipart = Multipart::new().with_file("f", &path);
+        assert!(multipart.is_replayable());
+        for _ in 0..2 {
+            assert!(render(&multipart).await.unwrap().contains("from disk"));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use std::sync::Mutex;
+use tokio::time::{sleep_until, Duration, Instant};
+
+/// The slowest rate a limiter accepts: one request a day
+pub const MIN_PER_SECOND: f64 = 1.0 / 86_400.0;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+/// A token bucket shared by everything that sends to the same rate-limited API
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Allow `per_second` requests per second on average and up to `burst` at once.
+    ///
+    /// `per_second` must be finite and at least [`MIN_PER_SECOND`].
+    pub fn new(per_second: f64, burst: u32) -> HttpResult<Self> {
+        if !per_second.is_finite() || per_second < MIN_PER_SECOND {
+            return Err(HttpError::Config(format!(
+                "rate limit must be a finite number of requests per second of at least \
+                 {MIN_PER_SECOND}, got {per_second}"
+            )));
+        }
+        let burst = f64::from(burst.max(1));
+        Ok(Self {
+            per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        })
+    }
+
+    /// Wait until a request may be sent
+    pub async fn acquire(&self) {
+        loop {
+            let ready_at = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                match bucket.paused_until {
+                    Some(until) if until > now => until,
+                    _ => {
+                        bucket.paused_until = None;
+                        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+                        bucket.refilled_at = now;
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        now + Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second)
+                    }
+                }
+            };
+            sleep_until(ready_at).await;
+        }
+    }
+
+    /// Hold back every caller for a while, e.g. after the server answered 429
+    pub fn pause(&self, duration: Duration) {
+        let mut bucket = self.bucket.lock().unwrap();
+        let until = Instant::now() + duration;
+        if bucket.paused_until.is_none_or(|current| current < until) {
+            bucket.paused_until = Some(until);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_must_be_finite_and_not_too_small() {
+        for rate in [
+            0.0,
+            -1.0,
+            1e-300,
+            MIN_PER_SECOND / 2.0,
+            f64::NAN,
+            f64::INFINITY,
+        ] {
+            assert!(
+                matches!(RateLimiter::new(rate, 1), Err(HttpError::Config(_))),
+                "{rate}"
+            );
+        }
+        assert!(RateLimiter::new(0.5, 0).is_ok());
+        assert!(RateLimiter::new(MIN_PER_SECOND, 1).is_ok());
+    }
+
+    #[tokio::test]
+    async fn burst_is_served_immediately_then_requests_are_spaced() {
+        let limiter = RateLimiter::new(20.0, 2).unwrap();
+        let started = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(started.elapsed() < Duration::from_millis(25));
+        limiter.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn slowest_rate_waits_without_overflowing() {
+        let limiter = RateLimiter::new(MIN_PER_SECOND, 1).unwrap();
+        limiter.acquire().await;
+        let second = tokio::time::timeout(Duration::from_millis(20), limiter.acquire()).await;
+        assert!(second.is_err());
+    }
+
+    #[tokio::test]
+    async fn pause_holds_back_callers_and_only_extends() {
+        let limiter = RateLimiter::new(1000.0, 10).unwrap();
+        limiter.pause(Duration::from_millis(60));
+        limiter.pause(Duration::from_millis(1));
+        let started = Instant::now();
+        limiter.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,96 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_headers::HttpHeaders;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::http_status_code::HttpStatusCode;
+use crate::http_url::HttpUrl;
+use crate::transport::{header_map, wire_body, Transport, WireBody};
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+
+/// The default transport, backed by a `reqwest::Client`
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: &HttpRequest, url: HttpUrl) -> HttpResult<HttpResponse> {
+        let reqwest_request = to_reqwest_request(request, url)?;
+        let response = self
+            .client
+            .execute(reqwest_request)
+            .await
+            .map_err(transport_error)?;
+        let status = HttpStatusCode::from(response.status());
+        let headers = HttpHeaders::from(response.headers());
+        let url = HttpUrl(response.url().clone());
+        let http_response = if request.is_streaming_response() && !status.is_error() {
+            HttpResponse::streaming(
+                status,
+                headers,
+                response.bytes_stream().map_err(transport_error),
+            )
+        } else {
+            let body = response.bytes().await.map_err(transport_error)?;
+            HttpResponse::from_parts(status, headers, body)
+        };
+        Ok(http_response.with_url(url))
+    }
+}
+
+/// Convert a request into the `reqwest::Request` for one attempt against `url`
+pub(crate) fn to_reqwest_request(
+    request: &HttpRequest,
+    url: HttpUrl,
+) -> HttpResult<reqwest::Request> {
+    let mut reqwest_request = reqwest::Request::new(request.method.clone().into(), url.0);
+    *reqwest_request.headers_mut() = header_map(request)?;
+    *reqwest_request.body_mut() = match wire_body(request)? {
+        WireBody::Empty => None,
+        WireBody::Bytes(bytes) => Some(reqwest::Body::from(bytes)),
+        WireBody::Stream(stream) => Some(reqwest::Body::wrap_stream(stream)),
+    };
+    Ok(reqwest_request)
+}
+
+fn transport_error(error: reqwest::Error) -> HttpError {
+    #[cfg(feature = "rustls")]
+    if let Some(mismatch) = crate::tls::find_pin_mismatch(&error) {
+        return HttpError::PinMismatch {
+            host: mismatch.host,
+            observed: mismatch.observed,
+        };
+    }
+    HttpError::from(error)
+}
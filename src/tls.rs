@@ -0,0 +1,591 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use crate::error::{HttpError, HttpResult};
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use base64::Engine;
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use rustls::client::WebPkiServerVerifier;
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use rustls::crypto::WebPkiSupportedAlgorithms;
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use rustls::pki_types::pem::PemObject;
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, RootCertStore, SignatureScheme};
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use sha2::{Digest, Sha256};
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use std::error::Error as StdError;
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+use std::sync::Arc;
+
+/// TLS settings for [`crate::http_client::ReqwestClientBuilder::tls`]
+#[derive(Clone)]
+pub struct TlsConfig {
+    root_certificates_pem: Vec<Vec<u8>>,
+    built_in_roots: bool,
+    client_identity_pem: Option<(Vec<u8>, Vec<u8>)>,
+    spki_pins: Vec<String>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            root_certificates_pem: Vec::new(),
+            built_in_roots: true,
+            client_identity_pem: None,
+            spki_pins: Vec::new(),
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the CA certificates in a PEM bundle, e.g. an egress proxy's private CA
+    pub fn with_root_certificates_pem(mut self, pem: impl AsRef<[u8]>) -> Self {
+        self.root_certificates_pem.push(pem.as_ref().to_vec());
+        self
+    }
+
+    /// Whether to trust the bundled Mozilla root store (on by default)
+    pub fn with_built_in_roots(mut self, enabled: bool) -> Self {
+        self.built_in_roots = enabled;
+        self
+    }
+
+    /// Present a client certificate chain and private key for mutual TLS
+    pub fn with_client_identity_pem(
+        mut self,
+        cert_chain: impl AsRef<[u8]>,
+        private_key: impl AsRef<[u8]>,
+    ) -> Self {
+        self.client_identity_pem =
+            Some((cert_chain.as_ref().to_vec(), private_key.as_ref().to_vec()));
+        self
+    }
+
+    /// Pin a public key as `sha256/<base64 SHA-256 of the DER SubjectPublicKeyInfo>`.
+    ///
+    /// When any pins are set, the handshake only succeeds if the server's certificate chains
+    /// to a trusted root through a path containing a pinned key: the server's own
+    /// certificate, an intermediate on that path, or the root it ends at (which the server
+    /// doesn't need to send). Certificates the server sends that aren't on the path never
+    /// match.
+    pub fn with_spki_pin(mut self, pin: impl Into<String>) -> Self {
+        self.spki_pins.push(pin.into());
+        self
+    }
+
+    #[cfg(any(feature = "reqwest", feature = "hyper"))]
+    pub(crate) fn build(&self) -> HttpResult<rustls::ClientConfig> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+        let mut roots = RootCertStore::empty();
+        if self.built_in_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        for pem in &self.root_certificates_pem {
+            for cert in CertificateDer::pem_slice_iter(pem) {
+                let cert = cert.map_err(|e| tls_error("invalid root certificate PEM", e))?;
+                roots
+                    .add(cert)
+                    .map_err(|e| tls_error("unusable root certificate", e))?;
+            }
+        }
+        if roots.is_empty() {
+            return Err(HttpError::Tls(
+                "no trusted root certificates configured".to_string(),
+            ));
+        }
+
+        let pins = self
+            .spki_pins
+            .iter()
+            .map(|pin| parse_pin(pin))
+            .collect::<HttpResult<Vec<_>>>()?;
+
+        let roots = Arc::new(roots);
+        let verifier = WebPkiServerVerifier::builder_with_provider(roots.clone(), provider.clone())
+            .build()
+            .map_err(|e| tls_error("failed to build certificate verifier", e))?;
+
+        let algorithms = provider.signature_verification_algorithms;
+        let builder = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| tls_error("unsupported protocol versions", e))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinningVerifier {
+                inner: verifier,
+                roots,
+                algorithms,
+                pins,
+            }));
+
+        let mut config = match &self.client_identity_pem {
+            Some((cert_pem, key_pem)) => {
+                let chain = CertificateDer::pem_slice_iter(cert_pem)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| tls_error("invalid client certificate PEM", e))?;
+                let key = PrivateKeyDer::from_pem_slice(key_pem)
+                    .map_err(|e| tls_error("invalid client private key PEM", e))?;
+                builder
+                    .with_client_auth_cert(chain, key)
+                    .map_err(|e| tls_error("unusable client identity", e))?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
+
+/// Raised during the handshake when no verified path for the server's certificate contains
+/// a pinned key
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(
+    "no pinned public key matched the certificate chain for {host} (server key: sha256/{observed})"
+)]
+pub struct PinMismatchError {
+    pub host: String,
+    pub observed: String,
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+#[derive(Debug)]
+struct PinningVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    // The same roots and algorithms `inner` uses, to rebuild the path for pin checks
+    roots: Arc<RootCertStore>,
+    algorithms: WebPkiSupportedAlgorithms,
+    pins: Vec<[u8; 32]>,
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+impl PinningVerifier {
+    // Whether some path from `end_entity` to a trusted root contains a pinned key. The
+    // pin check runs as part of path building, so a path that fails it doesn't stop
+    // another (pinned) path from being found.
+    fn has_pinned_path(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> bool {
+        let Ok(cert) = webpki::EndEntityCert::try_from(end_entity) else {
+            return false;
+        };
+        let pinned = |path: &webpki::VerifiedPath<'_>| {
+            let anchor = &path.anchor().subject_public_key_info;
+            // Trust anchors keep only the contents of the SubjectPublicKeyInfo SEQUENCE
+            let anchor_hash: [u8; 32] = Sha256::new()
+                .chain_update(der_header(0x30, anchor.len()))
+                .chain_update(anchor)
+                .finalize()
+                .into();
+            let on_path = std::iter::once(path.end_entity().subject_public_key_info())
+                .chain(
+                    path.intermediate_certificates()
+                        .map(|cert| cert.subject_public_key_info()),
+                )
+                .map(|spki| Sha256::digest(spki.as_ref()).into())
+                .chain(std::iter::once(anchor_hash))
+                .any(|hash: [u8; 32]| self.pins.contains(&hash));
+            if on_path {
+                Ok(())
+            } else {
+                Err(webpki::Error::UnknownIssuer)
+            }
+        };
+        cert.verify_for_usage(
+            self.algorithms.all,
+            &self.roots.roots,
+            intermediates,
+            now,
+            webpki::KeyUsage::server_auth(),
+            None,
+            Some(&pinned),
+        )
+        .is_ok()
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        if self.pins.is_empty() {
+            return Ok(verified);
+        }
+
+        if self.has_pinned_path(end_entity, intermediates, now) {
+            return Ok(verified);
+        }
+
+        let observed = spki_sha256(end_entity)
+            .map(|hash| base64::engine::general_purpose::STANDARD.encode(hash))
+            .unwrap_or_else(|| "<unparseable>".to_string());
+        Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+            OtherError(Arc::new(PinMismatchError {
+                host: server_name.to_str().into_owned(),
+                observed,
+            })),
+        )))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Find a pin mismatch anywhere in the source chain of a transport error
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+pub(crate) fn find_pin_mismatch(error: &(dyn StdError + 'static)) -> Option<PinMismatchError> {
+    let mut current = Some(error);
+    while let Some(err) = current {
+        if let Some(mismatch) = err.downcast_ref::<PinMismatchError>() {
+            return Some(mismatch.clone());
+        }
+        if let Some(
+            rustls::Error::InvalidCertificate(CertificateError::Other(other))
+            | rustls::Error::Other(other),
+        ) = err.downcast_ref::<rustls::Error>()
+        {
+            return other.0.downcast_ref::<PinMismatchError>().cloned();
+        }
+        // io::Error::source skips the wrapped error itself, so look inside explicitly
+        if let Some(inner) = err
+            .downcast_ref::<std::io::Error>()
+            .and_then(|io| io.get_ref())
+        {
+            if let Some(mismatch) = find_pin_mismatch(inner) {
+                return Some(mismatch);
+            }
+        }
+        current = err.source();
+    }
+    None
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+fn parse_pin(pin: &str) -> HttpResult<[u8; 32]> {
+    let encoded = pin
+        .trim()
+        .strip_prefix("sha256/")
+        .ok_or_else(|| HttpError::Tls(format!("SPKI pin '{pin}' must start with 'sha256/'")))?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| tls_error("SPKI pin is not valid base64", e))?;
+    bytes
+        .try_into()
+        .map_err(|_| HttpError::Tls(format!("SPKI pin '{pin}' is not a SHA-256 digest")))
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+fn tls_error(context: &str, error: impl std::fmt::Display) -> HttpError {
+    HttpError::Tls(format!("{context}: {error}"))
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+fn spki_sha256(cert: &CertificateDer<'_>) -> Option<[u8; 32]> {
+    spki_der(cert.as_ref()).map(|spki| Sha256::digest(spki).into())
+}
+
+// Walk just enough of the X.509 DER structure to find the SubjectPublicKeyInfo:
+// Certificate -> TBSCertificate -> [version], serial, signature, issuer, validity, subject, spki
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+fn spki_der(cert: &[u8]) -> Option<&[u8]> {
+    let (_, certificate, _) = der_element(cert)?;
+    let (_, tbs, _) = der_element(certificate)?;
+    let mut rest = tbs;
+    let (tag, _, _) = der_element(rest)?;
+    if tag == 0xa0 {
+        rest = der_element(rest)?.2;
+    }
+    for _ in 0..5 {
+        rest = der_element(rest)?.2;
+    }
+    let (tag, _, after) = der_element(rest)?;
+    if tag != 0x30 {
+        return None;
+    }
+    Some(&rest[..rest.len() - after.len()])
+}
+
+// The DER tag and length that precede `len` bytes of contents
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+fn der_header(tag: u8, len: usize) -> Vec<u8> {
+    let mut header = vec![tag];
+    if len < 0x80 {
+        header.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        header.push(0x80 | bytes.len() as u8);
+        header.extend(bytes);
+    }
+    header
+}
+
+// Split one DER element into its tag, contents and the bytes that follow it
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *input.first()?;
+    let first = *input.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let len = input
+            .get(2..2 + count)?
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, 2 + count)
+    };
+    let contents = input.get(header..header.checked_add(len)?)?;
+    Some((tag, contents, &input[header + len..]))
+}
+
+#[cfg(all(test, any(feature = "reqwest", feature = "hyper")))]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+    // A root CA that issued the leaf directly, plus an intermediate CA (`sibling`) that is
+    // also trusted through the root but is not on the leaf's path
+    struct Chain {
+        leaf: CertificateDer<'static>,
+        leaf_key: Vec<u8>,
+        ca: CertificateDer<'static>,
+        ca_key: Vec<u8>,
+        sibling: CertificateDer<'static>,
+        sibling_key: Vec<u8>,
+    }
+
+    fn chain() -> Chain {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.clone().self_signed(&ca_key).unwrap();
+        let leaf_key = KeyPair::generate().unwrap();
+        let leaf = CertificateParams::new(vec!["example.com".to_string()])
+            .unwrap()
+            .signed_by(&leaf_key, &ca, &ca_key)
+            .unwrap();
+        let sibling_key = KeyPair::generate().unwrap();
+        let sibling = ca_params.signed_by(&sibling_key, &ca, &ca_key).unwrap();
+        Chain {
+            leaf: leaf.der().clone(),
+            leaf_key: leaf_key.public_key_der(),
+            ca: ca.der().clone(),
+            ca_key: ca_key.public_key_der(),
+            sibling: sibling.der().clone(),
+            sibling_key: sibling_key.public_key_der(),
+        }
+    }
+
+    fn verifier(chain: &Chain, pins: Vec<[u8; 32]>) -> PinningVerifier {
+        let mut roots = RootCertStore::empty();
+        roots.add(chain.ca.clone()).unwrap();
+        let roots = Arc::new(roots);
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        PinningVerifier {
+            inner: WebPkiServerVerifier::builder_with_provider(roots.clone(), provider.clone())
+                .build()
+                .unwrap(),
+            roots,
+            algorithms: provider.signature_verification_algorithms,
+            pins,
+        }
+    }
+
+    fn verify_with(
+        verifier: &PinningVerifier,
+        chain: &Chain,
+        intermediates: &[CertificateDer<'static>],
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        verifier.verify_server_cert(
+            &chain.leaf,
+            intermediates,
+            &ServerName::try_from("example.com").unwrap(),
+            &[],
+            UnixTime::now(),
+        )
+    }
+
+    fn verify(
+        verifier: &PinningVerifier,
+        chain: &Chain,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        verify_with(verifier, chain, std::slice::from_ref(&chain.ca))
+    }
+
+    fn digest(bytes: &[u8]) -> [u8; 32] {
+        Sha256::digest(bytes).into()
+    }
+
+    #[test]
+    fn spki_walker_finds_the_subject_public_key_info() {
+        let chain = chain();
+        assert_eq!(
+            spki_der(chain.leaf.as_ref()),
+            Some(chain.leaf_key.as_slice())
+        );
+        assert_eq!(spki_der(chain.ca.as_ref()), Some(chain.ca_key.as_slice()));
+        assert_eq!(spki_sha256(&chain.leaf), Some(digest(&chain.leaf_key)));
+    }
+
+    #[test]
+    fn spki_walker_rejects_truncated_certificates() {
+        let chain = chain();
+        let der = chain.leaf.as_ref();
+        for len in [0, 1, 2, 10, der.len() / 2, der.len() - 1] {
+            assert_eq!(spki_der(&der[..len]), None, "truncated to {len} bytes");
+        }
+    }
+
+    #[test]
+    fn der_element_reads_short_and_long_form_lengths() {
+        assert_eq!(
+            der_element(&[0x04, 0x02, 0xaa, 0xbb, 0xcc]),
+            Some((0x04, &[0xaa, 0xbb][..], &[0xcc][..]))
+        );
+        let mut long = vec![0x30, 0x82, 0x01, 0x00];
+        long.extend([0x55; 256]);
+        let (tag, contents, rest) = der_element(&long).unwrap();
+        assert_eq!((tag, contents.len(), rest.len()), (0x30, 256, 0));
+        // indefinite length, oversized length fields and contents past the end
+        assert_eq!(der_element(&[0x30, 0x80, 0x00, 0x00]), None);
+        assert_eq!(der_element(&[0x30, 0x85, 1, 1, 1, 1, 1]), None);
+        assert_eq!(der_element(&[0x30, 0x82, 0x01]), None);
+        assert_eq!(der_element(&[0x30, 0x03, 0x00]), None);
+    }
+
+    #[test]
+    fn parse_pin_requires_a_base64_sha256_digest() {
+        let hash = digest(b"key");
+        let pin = format!(
+            "sha256/{}",
+            base64::engine::general_purpose::STANDARD.encode(hash)
+        );
+        assert_eq!(parse_pin(&format!(" {pin} ")).unwrap(), hash);
+        for bad in ["md5/AAAA", "sha256/not base64!", "sha256/AAAA"] {
+            assert!(matches!(parse_pin(bad), Err(HttpError::Tls(_))), "{bad}");
+        }
+    }
+
+    #[test]
+    fn verifier_accepts_a_pin_on_the_verified_path() {
+        let chain = chain();
+        assert!(verify(&verifier(&chain, Vec::new()), &chain).is_ok());
+        assert!(verify(&verifier(&chain, vec![digest(&chain.leaf_key)]), &chain).is_ok());
+        let ca_pinned = verifier(&chain, vec![digest(b"other"), digest(&chain.ca_key)]);
+        assert!(verify(&ca_pinned, &chain).is_ok());
+        // the root matches even when the server doesn't send it
+        assert!(verify_with(&ca_pinned, &chain, &[]).is_ok());
+    }
+
+    #[test]
+    fn verifier_ignores_pinned_certificates_off_the_verified_path() {
+        let chain = chain();
+        let pinned = verifier(&chain, vec![digest(&chain.sibling_key)]);
+        // a trusted, pinned CA appended to a chain it didn't issue doesn't satisfy the pin
+        let padded = [chain.ca.clone(), chain.sibling.clone()];
+        let error = verify_with(&pinned, &chain, &padded).unwrap_err();
+        assert!(find_pin_mismatch(&error).is_some(), "{error}");
+    }
+
+    #[test]
+    fn der_header_encodes_short_and_long_lengths() {
+        assert_eq!(der_header(0x30, 5), [0x30, 0x05]);
+        assert_eq!(der_header(0x30, 0x80), [0x30, 0x81, 0x80]);
+        assert_eq!(der_header(0x30, 0x0122), [0x30, 0x82, 0x01, 0x22]);
+    }
+
+    #[test]
+    fn verifier_reports_a_mismatch_that_can_be_found_through_wrappers() {
+        let chain = chain();
+        let error = verify(&verifier(&chain, vec![digest(b"other")]), &chain).unwrap_err();
+
+        let wrapped = std::io::Error::other(error.clone());
+        let mismatch = find_pin_mismatch(&wrapped).unwrap();
+        assert_eq!(mismatch.host, "example.com");
+        assert_eq!(
+            mismatch.observed,
+            base64::engine::general_purpose::STANDARD.encode(digest(&chain.leaf_key))
+        );
+        assert!(find_pin_mismatch(&error).is_some());
+        let unrelated = std::io::Error::other("reset");
+        assert!(find_pin_mismatch(&unrelated).is_none());
+    }
+
+    #[test]
+    fn verifier_still_enforces_chain_validation() {
+        let presented = chain();
+        let other = chain();
+        // a pin matching the presented leaf does not make an untrusted chain acceptable
+        let pinned = verifier(&other, vec![digest(&presented.leaf_key)]);
+        let error = verify(&pinned, &presented).unwrap_err();
+        assert!(
+            matches!(error, rustls::Error::InvalidCertificate(_)),
+            "{error}"
+        );
+        assert!(find_pin_mismatch(&error).is_none());
+    }
+}
@@ -0,0 +1,73 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_request::{HttpRequest, RequestBody};
+use crate::http_response::HttpResponse;
+use crate::http_url::HttpUrl;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use std::io;
+
+/// Sends a single attempt of a request over the wire.
+///
+/// The client resolves URLs and handles retries, endpoints, idempotency keys and
+/// interceptors, so every transport shares the same request semantics. Configure one with
+/// [`ReqwestClientBuilder::transport`](crate::http_client::ReqwestClientBuilder::transport).
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Send `request` to `url`, which already carries the query parameters.
+    ///
+    /// Bodies are read into memory unless the request asks for a streaming response and
+    /// the status is not an error.
+    async fn send(&self, request: &HttpRequest, url: HttpUrl) -> HttpResult<HttpResponse>;
+}
+
+/// A request body ready to be written for one attempt
+pub enum WireBody {
+    Empty,
+    Bytes(Bytes),
+    Stream(BoxStream<'static, io::Result<Bytes>>),
+}
+
+/// The request headers, plus the content type its body needs
+pub fn header_map(request: &HttpRequest) -> HttpResult<HeaderMap> {
+    let mut headers = HeaderMap::try_from(&request.headers)?;
+    match &request.body {
+        RequestBody::Json(_) if !headers.contains_key(CONTENT_TYPE) => {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        }
+        // The boundary in the content type must match the generated body
+        RequestBody::Multipart(multipart) => {
+            let content_type = HeaderValue::from_str(&multipart.content_type())
+                .map_err(|e| HttpError::Custom(format!("Invalid multipart boundary: {e}")))?;
+            headers.insert(CONTENT_TYPE, content_type);
+        }
+        _ => {}
+    }
+    Ok(headers)
+}
+
+/// Build the body for one attempt; multipart bodies are regenerated every time
+pub fn wire_body(request: &HttpRequest) -> HttpResult<WireBody> {
+    Ok(match &request.body {
+        RequestBody::Empty => WireBody::Empty,
+        RequestBody::Json(json) => WireBody::Bytes(Bytes::from(json.to_string())),
+        RequestBody::Bytes(bytes) => WireBody::Bytes(bytes.clone()),
+        RequestBody::Multipart(multipart) => WireBody::Stream(multipart.to_stream()?),
+    })
+}
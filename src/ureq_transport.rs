@@ -0,0 +1,123 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{HttpError, HttpResult};
+use crate::http_headers::HttpHeaders;
+use crate::http_request::HttpRequest;
+use crate::http_response::HttpResponse;
+use crate::http_status_code::HttpStatusCode;
+use crate::http_url::HttpUrl;
+use crate::transport::{header_map, wire_body, Transport, WireBody};
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures_util::TryStreamExt;
+
+/// A lightweight transport backed by a blocking `ureq` agent.
+///
+/// Each attempt runs on tokio's blocking thread pool. Response bodies are always read into
+/// memory, and an attempt that times out keeps its thread until ureq gives up, so set
+/// ureq's own timeouts on the agent for long-running calls.
+#[derive(Debug, Clone)]
+pub struct UreqTransport {
+    agent: ureq::Agent,
+}
+
+impl Default for UreqTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UreqTransport {
+    pub fn new() -> Self {
+        Self::from_agent(ureq::Agent::new_with_defaults())
+    }
+
+    /// Use an agent configured elsewhere; error statuses are still returned as responses
+    pub fn from_agent(agent: ureq::Agent) -> Self {
+        Self { agent }
+    }
+
+    pub fn agent(&self) -> &ureq::Agent {
+        &self.agent
+    }
+}
+
+#[async_trait]
+impl Transport for UreqTransport {
+    async fn send(&self, request: &HttpRequest, url: HttpUrl) -> HttpResult<HttpResponse> {
+        let mut builder = http::Request::builder()
+            .method(http::Method::from(request.method.clone()))
+            .uri(url.0.as_str());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = header_map(request)?;
+        }
+        // ureq writes bodies from a blocking reader, so streamed bodies are collected first
+        let body = match wire_body(request)? {
+            WireBody::Empty => None,
+            WireBody::Bytes(bytes) => Some(bytes),
+            WireBody::Stream(stream) => Some(
+                stream
+                    .try_fold(BytesMut::new(), |mut body, chunk| async move {
+                        body.extend_from_slice(&chunk);
+                        Ok(body)
+                    })
+                    .await?
+                    .freeze(),
+            ),
+        };
+        let (parts, ()) = builder.body(()).map_err(transport_error)?.into_parts();
+
+        let agent = self.agent.clone();
+        let (status, headers, body) = tokio::task::spawn_blocking(move || {
+            let mut response = match body {
+                Some(body) => agent.run(pass_statuses(
+                    &agent,
+                    http::Request::from_parts(parts, body.to_vec()),
+                )),
+                None => agent.run(pass_statuses(&agent, http::Request::from_parts(parts, ()))),
+            }
+            .map_err(transport_error)?;
+            let body = response
+                .body_mut()
+                .with_config()
+                .limit(u64::MAX)
+                .read_to_vec()
+                .map_err(transport_error)?;
+            Ok::<_, HttpError>((response.status(), response.headers().clone(), body))
+        })
+        .await
+        .map_err(transport_error)??;
+
+        let status = HttpStatusCode::from(status);
+        let headers = HttpHeaders::from(&headers);
+        Ok(HttpResponse::from_parts(status, headers, Bytes::from(body)).with_url(url))
+    }
+}
+
+// Return error statuses as responses so the client can decide what to retry
+fn pass_statuses<B: ureq::AsSendBody>(
+    agent: &ureq::Agent,
+    request: http::Request<B>,
+) -> http::Request<B> {
+    agent
+        .configure_request(request)
+        .http_status_as_error(false)
+        .build()
+}
+
+fn transport_error(error: impl std::error::Error + Send + Sync + 'static) -> HttpError {
+    HttpError::Transport(Box::new(error))
+}
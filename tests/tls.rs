@@ -0,0 +1,284 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+// TLS against a local server with certificates generated per test run: custom roots,
+// client identities (mutual TLS) and SPKI pins.
+use base64::Engine;
+use core_rs::error::HttpError;
+use core_rs::http_client::{HttpClient, ReqwestClient};
+use core_rs::http_method::HttpMethod;
+use core_rs::http_request::HttpRequest;
+use core_rs::tls::TlsConfig;
+use rcgen::{BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::RootCertStore;
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+struct Pki {
+    ca_pem: String,
+    ca_spki: Vec<u8>,
+    // A second CA issued by the test CA, which didn't issue the server certificate
+    sibling_ca_pem: String,
+    sibling_ca_spki: Vec<u8>,
+    server_cert_pem: String,
+    server_chain_pem: String,
+    server_key_pem: String,
+    server_spki: Vec<u8>,
+    client_cert_pem: String,
+    client_key_pem: String,
+}
+
+fn pki() -> Pki {
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "core_rs test CA");
+    let ca = ca_params.clone().self_signed(&ca_key).unwrap();
+    let sibling_key = KeyPair::generate().unwrap();
+    let sibling = ca_params.signed_by(&sibling_key, &ca, &ca_key).unwrap();
+
+    let server_key = KeyPair::generate().unwrap();
+    let mut server_params =
+        CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+    server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    let server = server_params.signed_by(&server_key, &ca, &ca_key).unwrap();
+
+    let client_key = KeyPair::generate().unwrap();
+    let mut client_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    client_params
+        .distinguished_name
+        .push(DnType::CommonName, "core_rs test client");
+    client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+    Pki {
+        ca_pem: ca.pem(),
+        ca_spki: ca_key.public_key_der(),
+        sibling_ca_pem: sibling.pem(),
+        sibling_ca_spki: sibling_key.public_key_der(),
+        server_cert_pem: server.pem(),
+        server_chain_pem: format!("{}{}", server.pem(), ca.pem()),
+        server_key_pem: server_key.serialize_pem(),
+        server_spki: server_key.public_key_der(),
+        client_cert_pem: client.pem(),
+        client_key_pem: client_key.serialize_pem(),
+    }
+}
+
+fn pin(spki: &[u8]) -> String {
+    format!(
+        "sha256/{}",
+        base64::engine::general_purpose::STANDARD.encode(Sha256::digest(spki))
+    )
+}
+
+// An HTTPS server answering every request with `200 ok`, optionally requiring a client
+// certificate issued by the test CA
+async fn serve(pki: &Pki, require_client_cert: bool) -> SocketAddr {
+    serve_chain(pki, &pki.server_chain_pem, require_client_cert).await
+}
+
+// Like `serve`, presenting the certificates in `chain_pem`
+async fn serve_chain(pki: &Pki, chain_pem: &str, require_client_cert: bool) -> SocketAddr {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let chain: Vec<CertificateDer<'static>> = CertificateDer::pem_slice_iter(chain_pem.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let key = PrivateKeyDer::from_pem_slice(pki.server_key_pem.as_bytes()).unwrap();
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .unwrap();
+    let config = if require_client_cert {
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_slice_iter(pki.ca_pem.as_bytes()) {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+            .build()
+            .unwrap();
+        builder
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(chain, key)
+            .unwrap()
+    } else {
+        builder
+            .with_no_client_auth()
+            .with_single_cert(chain, key)
+            .unwrap()
+    };
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let Ok((tcp, _)) = listener.accept().await else {
+                return;
+            };
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let Ok(mut tls) = acceptor.accept(tcp).await else {
+                    return;
+                };
+                let mut buf = [0u8; 4096];
+                let _ = tls.read(&mut buf).await;
+                let _ = tls
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    )
+                    .await;
+                let _ = tls.shutdown().await;
+            });
+        }
+    });
+    addr
+}
+
+async fn get(addr: SocketAddr, tls: TlsConfig) -> Result<String, HttpError> {
+    let client = ReqwestClient::builder()
+        .base_url(format!("https://127.0.0.1:{}", addr.port()))
+        .tls(tls)
+        .build()?;
+    let request = HttpRequest::new(HttpMethod::Get, "/ping").unwrap();
+    client.execute(request).await?.text().await
+}
+
+fn trusting(pki: &Pki) -> TlsConfig {
+    TlsConfig::new()
+        .with_built_in_roots(false)
+        .with_root_certificates_pem(&pki.ca_pem)
+}
+
+#[tokio::test]
+async fn custom_root_is_trusted() {
+    let pki = pki();
+    let addr = serve(&pki, false).await;
+    assert_eq!(get(addr, trusting(&pki)).await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn unknown_ca_is_rejected_by_built_in_roots() {
+    let pki = pki();
+    let addr = serve(&pki, false).await;
+    let error = get(addr, TlsConfig::new()).await.unwrap_err();
+    assert!(!matches!(error, HttpError::PinMismatch { .. }), "{error}");
+}
+
+#[tokio::test]
+async fn client_identity_is_presented_for_mutual_tls() {
+    let pki = pki();
+    let addr = serve(&pki, true).await;
+    let with_identity =
+        trusting(&pki).with_client_identity_pem(&pki.client_cert_pem, &pki.client_key_pem);
+    assert_eq!(get(addr, with_identity).await.unwrap(), "ok");
+    assert!(get(addr, trusting(&pki)).await.is_err());
+}
+
+#[tokio::test]
+async fn matching_leaf_or_ca_pin_is_accepted() {
+    let pki = pki();
+    let addr = serve(&pki, false).await;
+    let leaf_pinned = trusting(&pki).with_spki_pin(pin(&pki.server_spki));
+    assert_eq!(get(addr, leaf_pinned).await.unwrap(), "ok");
+    let ca_pinned = trusting(&pki)
+        .with_spki_pin(pin(b"some other key"))
+        .with_spki_pin(pin(&pki.ca_spki));
+    assert_eq!(get(addr, ca_pinned).await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn ca_pin_matches_a_root_the_server_does_not_send() {
+    let pki = pki();
+    let addr = serve_chain(&pki, &pki.server_cert_pem, false).await;
+    let ca_pinned = trusting(&pki).with_spki_pin(pin(&pki.ca_spki));
+    assert_eq!(get(addr, ca_pinned).await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn pinned_certificate_appended_off_the_path_is_rejected() {
+    let pki = pki();
+    let padded = format!("{}{}", pki.server_chain_pem, pki.sibling_ca_pem);
+    let addr = serve_chain(&pki, &padded, false).await;
+    assert_eq!(get(addr, trusting(&pki)).await.unwrap(), "ok");
+    let pinned = trusting(&pki).with_spki_pin(pin(&pki.sibling_ca_spki));
+    assert!(matches!(
+        get(addr, pinned).await,
+        Err(HttpError::PinMismatch { .. })
+    ));
+}
+
+#[tokio::test]
+async fn pin_mismatch_reports_the_observed_key() {
+    let pki = pki();
+    let addr = serve(&pki, false).await;
+    let pinned = trusting(&pki).with_spki_pin(pin(b"some other key"));
+    match get(addr, pinned).await {
+        Err(HttpError::PinMismatch { host, observed }) => {
+            assert_eq!(host, "127.0.0.1");
+            assert_eq!(format!("sha256/{observed}"), pin(&pki.server_spki));
+        }
+        other => panic!("expected a pin mismatch, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn invalid_tls_settings_fail_at_build() {
+    let bad_pin = TlsConfig::new().with_spki_pin("md5/abc");
+    assert!(matches!(
+        ReqwestClient::builder().tls(bad_pin).build(),
+        Err(HttpError::Tls(_))
+    ));
+    let no_roots = TlsConfig::new().with_built_in_roots(false);
+    assert!(matches!(
+        ReqwestClient::builder().tls(no_roots).build(),
+        Err(HttpError::Tls(_))
+    ));
+}
+
+#[cfg(feature = "hyper")]
+#[tokio::test]
+async fn hyper_transport_uses_the_same_tls_settings() {
+    use core_rs::hyper_transport::HyperTransport;
+
+    let pki = pki();
+    let addr = serve(&pki, true).await;
+    let send = |tls: TlsConfig| async move {
+        let client = ReqwestClient::builder()
+            .base_url(format!("https://127.0.0.1:{}", addr.port()))
+            .transport(HyperTransport::builder().tls(tls).build()?)
+            .build()?;
+        client
+            .execute(HttpRequest::new(HttpMethod::Get, "/ping").unwrap())
+            .await?
+            .text()
+            .await
+    };
+    let identity =
+        trusting(&pki).with_client_identity_pem(&pki.client_cert_pem, &pki.client_key_pem);
+    assert_eq!(send(identity.clone()).await.unwrap(), "ok");
+    let pinned = identity.with_spki_pin(pin(b"some other key"));
+    assert!(matches!(
+        send(pinned).await,
+        Err(HttpError::PinMismatch { .. })
+    ));
+}
@@ -0,0 +1,384 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+// A local HTTP/1.1 server and the checks every transport has to pass against it
+use async_trait::async_trait;
+use core_rs::error::{HttpError, TimeoutKind};
+use core_rs::http_client::{HttpClient, ReqwestClient};
+use core_rs::http_method::HttpMethod;
+use core_rs::http_request::{HttpRequest, RetryPolicy};
+use core_rs::http_response::HttpResponse;
+use core_rs::http_status_code::HttpStatusCode;
+use core_rs::interceptor::PreRequestInterceptor;
+use core_rs::multipart::Multipart;
+use core_rs::transport::Transport;
+use futures_util::TryStreamExt;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+const STREAM_LEN: usize = 64 * 1024;
+
+struct Request {
+    method: String,
+    target: String,
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+}
+
+// Answers one request per connection under `/api`:
+//   /echo          the request as JSON (headers only for HEAD)
+//   /status/<n>    status n with a JSON error body
+//   /flaky/<id>    drops the connection the first time an id is seen, 200 afterwards
+//   /slow          200 after a second
+//   /stream        a chunked body of STREAM_LEN bytes
+async fn serve() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let seen = Arc::new(Mutex::new(HashMap::<String, u32>::new()));
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                return;
+            };
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                if let Some(request) = read_request(&mut stream).await {
+                    respond(stream.get_mut(), request, &seen).await;
+                }
+            });
+        }
+    });
+    addr
+}
+
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    stream.read_line(&mut line).await.ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+    let mut headers = BTreeMap::new();
+    loop {
+        line.clear();
+        stream.read_line(&mut line).await.ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+    let mut body = Vec::new();
+    if headers.get("transfer-encoding").map(String::as_str) == Some("chunked") {
+        loop {
+            line.clear();
+            stream.read_line(&mut line).await.ok()?;
+            let size = usize::from_str_radix(line.trim().split(';').next()?, 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            stream.read_exact(&mut chunk).await.ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else if let Some(len) = headers.get("content-length") {
+        body.resize(len.parse().ok()?, 0);
+        stream.read_exact(&mut body).await.ok()?;
+    }
+    Some(Request {
+        method,
+        target,
+        headers,
+        body,
+    })
+}
+
+async fn respond(stream: &mut TcpStream, request: Request, seen: &Mutex<HashMap<String, u32>>) {
+    let (path, query) = request
+        .target
+        .split_once('?')
+        .unwrap_or((&request.target, ""));
+    let route = path.strip_prefix("/api").unwrap_or(path);
+    let (status, content_type, body) = match route {
+        "/echo" => {
+            let echo = json!({
+                "method": request.method,
+                "path": path,
+                "query": query,
+                "headers": request.headers,
+                "body": String::from_utf8_lossy(&request.body),
+            });
+            (200, "application/json", echo.to_string().into_bytes())
+        }
+        "/slow" => {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            (200, "text/plain", b"late".to_vec())
+        }
+        "/stream" => {
+            let head = "HTTP/1.1 200 OK\r\ncontent-type: application/octet-stream\r\n\
+                        transfer-encoding: chunked\r\nconnection: close\r\n\r\n";
+            let _ = stream.write_all(head.as_bytes()).await;
+            for chunk in stream_body().chunks(4096) {
+                let _ = stream
+                    .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                    .await;
+                let _ = stream.write_all(chunk).await;
+                let _ = stream.write_all(b"\r\n").await;
+                let _ = stream.flush().await;
+            }
+            let _ = stream.write_all(b"0\r\n\r\n").await;
+            return;
+        }
+        _ => {
+            if let Some(code) = route.strip_prefix("/status/") {
+                let body = json!({"message": format!("status {code}")});
+                (
+                    code.parse().unwrap(),
+                    "application/json",
+                    body.to_string().into_bytes(),
+                )
+            } else if let Some(id) = route.strip_prefix("/flaky/") {
+                let first = {
+                    let mut seen = seen.lock().unwrap();
+                    let count = seen.entry(id.to_string()).or_default();
+                    *count += 1;
+                    *count == 1
+                };
+                if first {
+                    return;
+                }
+                (200, "text/plain", b"recovered".to_vec())
+            } else {
+                (404, "text/plain", b"no such route".to_vec())
+            }
+        }
+    };
+    let head = format!(
+        "HTTP/1.1 {status} X\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\n\
+         x-served-by: conformance\r\nconnection: close\r\n\r\n",
+        body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    if request.method != "HEAD" {
+        let _ = stream.write_all(&body).await;
+    }
+    let _ = stream.shutdown().await;
+}
+
+fn stream_body() -> Vec<u8> {
+    (0..STREAM_LEN).map(|i| (i % 251) as u8).collect()
+}
+
+// Signs what the transport is expected to put on the wire, so the server side can check it
+struct TestSigner;
+
+fn signature(method: &str, path: &str, body: &[u8]) -> String {
+    let mut hash = Sha256::new();
+    hash.update(method.as_bytes());
+    hash.update(path.as_bytes());
+    hash.update(body);
+    hex::encode(hash.finalize())
+}
+
+#[async_trait]
+impl PreRequestInterceptor for TestSigner {
+    async fn intercept(&self, request: &mut HttpRequest) {
+        let credentials = request.signing_credentials();
+        let signature = signature(
+            credentials.method.as_str(),
+            &credentials.path,
+            credentials.body.as_deref().unwrap_or_default(),
+        );
+        request.add_header("x-test-signature", &signature).unwrap();
+    }
+}
+
+fn client(transport: impl Transport + 'static, addr: SocketAddr) -> ReqwestClient {
+    ReqwestClient::builder()
+        .base_url(format!("http://{addr}/api"))
+        .default_header("x-default", "on")
+        .pre_interceptor(Arc::new(TestSigner))
+        .transport(transport)
+        .build()
+        .unwrap()
+}
+
+async fn echo(response: HttpResponse) -> Value {
+    assert_eq!(response.status(), HttpStatusCode::Ok);
+    response.json_value().await.unwrap()
+}
+
+fn assert_signed(echo: &Value) {
+    let target = match echo["query"].as_str().unwrap() {
+        "" => echo["path"].as_str().unwrap().to_string(),
+        query => format!("{}?{query}", echo["path"].as_str().unwrap()),
+    };
+    let expected = signature(
+        echo["method"].as_str().unwrap(),
+        &target,
+        echo["body"].as_str().unwrap().as_bytes(),
+    );
+    assert_eq!(echo["headers"]["x-test-signature"], expected.as_str());
+}
+
+pub async fn get_sends_path_query_and_headers(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let request = HttpRequest::new(HttpMethod::Get, "/echo")
+        .unwrap()
+        .with_query_params(HashMap::from([
+            ("b".to_string(), "2".to_string()),
+            ("a".to_string(), "x y".to_string()),
+        ]))
+        .with_header("x-custom", "yes");
+    let response = client(transport, addr).execute(request).await.unwrap();
+    assert_eq!(response.header("x-served-by"), Some("conformance"));
+    assert_eq!(
+        response
+            .url()
+            .map(|url| url.0.path().to_string())
+            .as_deref(),
+        Some("/api/echo")
+    );
+    let echo = echo(response).await;
+    assert_eq!(echo["method"], "GET");
+    assert_eq!(echo["path"], "/api/echo");
+    assert_eq!(echo["query"], "a=x+y&b=2");
+    assert_eq!(echo["headers"]["x-custom"], "yes");
+    assert_eq!(echo["headers"]["x-default"], "on");
+    assert_signed(&echo);
+}
+
+pub async fn json_body_is_sent_as_json(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let body = json!({"product_id": "BTC-USD", "size": "0.5"});
+    let request = HttpRequest::new(HttpMethod::Post, "/echo")
+        .unwrap()
+        .with_json_body(body.clone());
+    let echo = echo(client(transport, addr).execute(request).await.unwrap()).await;
+    assert_eq!(echo["method"], "POST");
+    assert_eq!(echo["headers"]["content-type"], "application/json");
+    let sent: Value = serde_json::from_str(echo["body"].as_str().unwrap()).unwrap();
+    assert_eq!(sent, body);
+    assert_signed(&echo);
+}
+
+pub async fn multipart_body_is_streamed(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let multipart = Multipart::new().with_text("note", "hello");
+    let boundary = multipart.boundary().to_string();
+    let request = HttpRequest::new(HttpMethod::Put, "/echo")
+        .unwrap()
+        .with_multipart(multipart);
+    let echo = echo(client(transport, addr).execute(request).await.unwrap()).await;
+    let content_type = echo["headers"]["content-type"].as_str().unwrap();
+    assert!(
+        content_type.starts_with("multipart/form-data"),
+        "{content_type}"
+    );
+    assert!(content_type.contains(&boundary));
+    let body = echo["body"].as_str().unwrap();
+    assert!(
+        body.contains("name=\"note\"") && body.contains("hello"),
+        "{body}"
+    );
+}
+
+pub async fn error_statuses_carry_headers_and_body(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let request = HttpRequest::new(HttpMethod::Get, "/status/404").unwrap();
+    let error = client(transport, addr).execute(request).await.unwrap_err();
+    assert_eq!(error.status(), Some(HttpStatusCode::NotFound));
+    assert_eq!(
+        error.response_headers().and_then(|h| h.get("x-served-by")),
+        Some("conformance")
+    );
+    let body: Value = error.decode_body().unwrap();
+    assert_eq!(body["message"], "status 404");
+}
+
+pub async fn dropped_connections_are_retried(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let request = HttpRequest::new(HttpMethod::Get, "/flaky/retry")
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            backoff_millis: 10,
+        });
+    let response = client(transport, addr).execute(request).await.unwrap();
+    assert_eq!(response.attempts(), 2);
+    assert_eq!(response.text().await.unwrap(), "recovered");
+}
+
+pub async fn attempts_time_out(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let request = HttpRequest::new(HttpMethod::Get, "/slow")
+        .unwrap()
+        .with_timeout(Duration::from_millis(100));
+    let error = client(transport, addr).execute(request).await.unwrap_err();
+    assert!(
+        matches!(
+            error.root(),
+            HttpError::Timeout {
+                kind: TimeoutKind::Attempt,
+                ..
+            }
+        ),
+        "{error}"
+    );
+}
+
+pub async fn streaming_bodies_arrive_intact(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let request = HttpRequest::new(HttpMethod::Get, "/stream")
+        .unwrap()
+        .with_streaming_response(true);
+    let response = client(transport, addr).execute(request).await.unwrap();
+    let chunks: Vec<_> = response.bytes_stream().try_collect().await.unwrap();
+    assert_eq!(chunks.concat(), stream_body());
+}
+
+pub async fn head_responses_have_no_body(transport: impl Transport + 'static) {
+    let addr = serve().await;
+    let request = HttpRequest::new(HttpMethod::Head, "/echo").unwrap();
+    let response = client(transport, addr).execute(request).await.unwrap();
+    assert_eq!(response.status(), HttpStatusCode::Ok);
+    assert_eq!(response.header("x-served-by"), Some("conformance"));
+    assert!(response.body().is_none_or(|body| body.is_empty()));
+}
+
+pub async fn refused_connections_are_transport_errors(transport: impl Transport + 'static) {
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let request = HttpRequest::new(HttpMethod::Get, "/echo").unwrap();
+    let error = client(transport, closed)
+        .execute(request)
+        .await
+        .unwrap_err();
+    assert!(error.status().is_none(), "{error}");
+    assert!(
+        !matches!(error.root(), HttpError::Timeout { .. }),
+        "{error}"
+    );
+}
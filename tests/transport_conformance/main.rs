@@ -0,0 +1,83 @@
+/**
+ * Copyright 2025-present Coinbase Global, Inc.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *  http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+// The same behaviour checks run against every transport backend that is compiled in, with
+// a local HTTP/1.1 server on the other end.
+#[cfg(any(feature = "reqwest", feature = "hyper", feature = "ureq"))]
+mod cases;
+
+#[cfg(any(feature = "reqwest", feature = "hyper", feature = "ureq"))]
+macro_rules! conformance {
+    ($backend:ident, $transport:expr) => {
+        mod $backend {
+            #[tokio::test(flavor = "multi_thread")]
+            async fn get_sends_path_query_and_headers() {
+                crate::cases::get_sends_path_query_and_headers($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn json_body_is_sent_as_json() {
+                crate::cases::json_body_is_sent_as_json($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn multipart_body_is_streamed() {
+                crate::cases::multipart_body_is_streamed($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn error_statuses_carry_headers_and_body() {
+                crate::cases::error_statuses_carry_headers_and_body($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn dropped_connections_are_retried() {
+                crate::cases::dropped_connections_are_retried($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn attempts_time_out() {
+                crate::cases::attempts_time_out($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn streaming_bodies_arrive_intact() {
+                crate::cases::streaming_bodies_arrive_intact($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn head_responses_have_no_body() {
+                crate::cases::head_responses_have_no_body($transport).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            async fn refused_connections_are_transport_errors() {
+                crate::cases::refused_connections_are_transport_errors($transport).await;
+            }
+        }
+    };
+}
+
+#[cfg(feature = "reqwest")]
+conformance!(reqwest, core_rs::reqwest_transport::ReqwestTransport::new());
+
+#[cfg(feature = "hyper")]
+conformance!(
+    hyper,
+    core_rs::hyper_transport::HyperTransport::new().unwrap()
+);
+
+#[cfg(feature = "ureq")]
+conformance!(ureq, core_rs::ureq_transport::UreqTransport::new());